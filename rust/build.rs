@@ -0,0 +1,21 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+#[path = "src/sudoku/tables.rs"]
+mod tables;
+
+/// Writes `POSSIBLE` as an array literal for `list.rs` to include.
+fn main() {
+    println!("cargo:rerun-if-changed=src/sudoku/tables.rs");
+    let lists = tables::make_possibles()
+        .iter()
+        .map(|list| {
+            let values = list.iter().map(|value| value.to_string());
+            format!("[{}]", values.collect::<Vec<String>>().join(","))
+        })
+        .collect::<Vec<String>>();
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("possible.rs");
+    fs::write(path, format!("[{}]", lists.join(",\n"))).unwrap();
+}
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+use std::env;
+use std::process;
+
+use sudokumaci::sudoku::list;
+use sudokumaci::sudoku::tables;
+
+const USAGE: &str = "Usage: make-tables [ALL81|BIT9|GROUPS|BIT81|SET_CELLS|POSSIBLE]...\n       \
+                     make-tables --verify";
+
+/// The checksums of the tables as `list.rs` held them before they were generated, so that
+/// a change to a generator cannot pass unnoticed.
+const CHECKSUMS: [(&str, u64); 6] = [
+    ("ALL81", 0x59f1331b8d1282ea),
+    ("BIT9", 0x0043075a5f581e15),
+    ("GROUPS", 0x407f48c295afe932),
+    ("BIT81", 0xbe6fe14c1bba64ea),
+    ("SET_CELLS", 0x4d230cc9661710ea),
+    ("POSSIBLE", 0x7fb20b7593db00f5),
+];
+
+/// FNV-1a over the little endian bytes of each value.
+fn checksum(values: impl IntoIterator<Item = u128>) -> u64 {
+    values.into_iter().fold(0xcbf29ce484222325, |hash, value| {
+        value.to_le_bytes().iter().fold(hash, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+    })
+}
+
+fn print_list(declaration: &str, values: impl Iterator<Item = String>) {
+    println!("{} = [", declaration);
+    for value in values {
+        println!("    {},", value);
+    }
+    println!("];");
+}
+
+/// Prints one table as Rust source, laid out the way `list.rs` used to hold it.
+fn print_table(name: &str) -> bool {
+    match name {
+        "ALL81" => println!("pub const ALL81: u128 =\n    0b{:b};", tables::ALL81),
+        "BIT9" => print_list(
+            "pub const BIT9: [usize; 9]",
+            tables::make_digit_bits::<9>()
+                .iter()
+                .map(|bit| format!("0b{:09b}", bit)),
+        ),
+        "GROUPS" => print_list(
+            "pub const GROUPS: [u128; 27]",
+            tables::make_groups::<27>(3, 3)
+                .iter()
+                .map(|group| format!("0b{:b}", group)),
+        ),
+        "BIT81" => print_list(
+            "pub const BIT81: [u128; 81]",
+            tables::make_cell_bits::<81>()
+                .iter()
+                .map(|bit| format!("0b{:b}", bit)),
+        ),
+        "SET_CELLS" => print_list(
+            "pub const SET_CELLS: [u128; 81]",
+            tables::make_set_cells::<81>(&tables::make_groups::<27>(3, 3))
+                .iter()
+                .map(|cells| format!("0b{:081b}", cells)),
+        ),
+        "POSSIBLE" => print_list(
+            "pub static POSSIBLE: [[usize; 937]; 54]",
+            tables::make_possibles().iter().map(|list| {
+                let values = list.iter().map(|value| format!("        {},\n", value));
+                format!("[\n{}    ]", values.collect::<String>())
+            }),
+        ),
+        _ => return false,
+    }
+    true
+}
+
+/// Each full placement in `POSSIBLE`, with the position it was read from.
+fn placements() -> Vec<(String, u128)> {
+    let mut placements = vec![];
+    for (list, possible) in list::POSSIBLE.iter().enumerate() {
+        for run in 0..72 {
+            let second = possible[1 + run * 13];
+            for (index, third) in possible[2 + run * 13..14 + run * 13].iter().enumerate() {
+                let cells = (*third as u128) << 45 | (second as u128) << 18 | possible[0] as u128;
+                let at = format!("POSSIBLE[{}][{}]", list, 2 + run * 13 + index);
+                placements.push((at, cells));
+            }
+        }
+    }
+    placements
+}
+
+/// Checks the compiled tables against their generators and against the rules of the grid.
+fn verify() -> Vec<String> {
+    let mut failures = vec![];
+    let mut check = |ok: bool, failure: String| {
+        if !ok {
+            failures.push(failure);
+        }
+    };
+
+    let found = [
+        checksum([list::ALL81]),
+        checksum(list::BIT9.iter().map(|bit| *bit as u128)),
+        checksum(list::GROUPS),
+        checksum(list::BIT81),
+        checksum(list::SET_CELLS),
+        checksum(list::POSSIBLE.iter().flatten().map(|value| *value as u128)),
+    ];
+    for ((name, expected), found) in CHECKSUMS.iter().zip(found) {
+        check(
+            found == *expected,
+            format!("{} differs from the tables once pasted into list.rs", name),
+        );
+    }
+
+    for (cell, bit) in list::BIT81.iter().enumerate() {
+        check(
+            *bit == 1 << cell,
+            format!("BIT81[{}] is not cell {}", cell, cell),
+        );
+    }
+    for (index, group) in list::GROUPS.iter().enumerate() {
+        check(
+            group.count_ones() == 9 && group & !list::ALL81 == 0,
+            format!("GROUPS[{}] does not hold 9 cells", index),
+        );
+    }
+    for cell in 0..81 {
+        let groups = list::GROUPS
+            .iter()
+            .filter(|group| *group & 1 << cell != 0)
+            .collect::<Vec<&u128>>();
+        check(
+            groups.len() == 3,
+            format!("cell {} is not in one row, column and box", cell),
+        );
+        let peers = groups.iter().fold(0, |peers, group| peers | *group);
+        check(
+            list::SET_CELLS[cell] == (list::ALL81 & !peers) | 1 << cell,
+            format!(
+                "SET_CELLS[{}] does not clear the peers of cell {}",
+                cell, cell
+            ),
+        );
+    }
+
+    let placements = placements();
+    let mut seen = HashSet::new();
+    for (at, cells) in placements.iter() {
+        check(
+            list::GROUPS
+                .iter()
+                .all(|group| (cells & group).count_ones() == 1),
+            format!("{} is not a placement of one digit", at),
+        );
+        check(
+            (0..81)
+                .filter(|cell| cells & 1 << cell != 0)
+                .all(|cell| cells & list::SET_CELLS[cell] == *cells),
+            format!("{} is not kept by SET_CELLS", at),
+        );
+        check(seen.insert(*cells), format!("{} is a duplicate", at));
+    }
+    // One digit can be placed in 54 ways on the first two rows and 864 on the other seven.
+    check(
+        seen.len() == 46656,
+        format!("POSSIBLE covers {} placements of 46656", seen.len()),
+    );
+    failures
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|arg| arg == "--verify") {
+        let failures = verify();
+        for failure in failures.iter() {
+            eprintln!("{}", failure);
+        }
+        if !failures.is_empty() {
+            process::exit(1);
+        }
+        println!("tables verified");
+        return;
+    }
+
+    let names = if args.is_empty() {
+        vec!["ALL81", "BIT9", "GROUPS", "BIT81", "SET_CELLS", "POSSIBLE"]
+    } else {
+        args.iter().map(String::as_str).collect()
+    };
+    for (index, name) in names.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        if !print_table(name) {
+            eprintln!("{}", USAGE);
+            process::exit(1);
+        }
+    }
+}
@@ -1,31 +1,582 @@
-#[macro_use]
-extern crate lazy_static;
-
 use rayon::prelude::*;
 use std::env;
 use std::fs;
+use std::process;
+use std::time::{Duration, Instant};
+
+use sudokumaci::sudoku;
+use sudokumaci::sudoku::constraint;
+use sudokumaci::sudoku::variant;
+use sudokumaci::sudoku::Solver;
+
+/// Solves every puzzle of `filename` and checks the solutions against the rules and against
+/// the reference `puzzle,solution` lines, by default from the `.solved` file next to it.
+/// A valid solution other than the reference only counts as a mismatch when the puzzle has
+/// one solution. Returns the exit code.
+fn verify(filename: &str, reference: Option<&str>) -> i32 {
+  let reference = reference.map(String::from).unwrap_or_else(|| {
+    let stem = filename.strip_suffix(".sudokus").unwrap_or(filename);
+    format!("{}.solved", stem)
+  });
+  let (sudokus, solved) = match (fs::read_to_string(filename), fs::read_to_string(&reference)) {
+    (Ok(sudokus), Ok(solved)) => (sudokus, solved),
+    (Err(error), _) => {
+      eprintln!("{}: {}", filename, error);
+      return 2;
+    }
+    (_, Err(error)) => {
+      eprintln!("{}: {}", reference, error);
+      return 2;
+    }
+  };
+  let puzzles = sudokus.lines().collect::<Vec<&str>>();
+  let references = solved.lines().collect::<Vec<&str>>();
+  let solver = sudoku::solver::BitboardSolver::new(
+    sudoku::Variant::default(),
+    sudoku::SolverConfig::default(),
+  );
+  // Either of `0` and `.` marks an empty cell.
+  let same = |a: &str, b: &str| a.replace('.', "0") == b.replace('.', "0");
+  let results = puzzles
+    .par_iter()
+    .enumerate()
+    .map(|(index, puzzle)| {
+      let expected = match references.get(index).and_then(|line| line.split_once(',')) {
+        Some((reference_puzzle, _)) if !same(reference_puzzle, puzzle) => {
+          return Some("reference line is for another puzzle".to_string())
+        }
+        Some((_, expected)) => expected,
+        None => return Some("no reference line".into()),
+      };
+      if puzzle.len() != 81 {
+        return Some("not a 9x9 puzzle".into());
+      }
+      if let Err(violation) = sudoku::validate_solution(puzzle, expected) {
+        return Some(format!("reference solution: {}", violation));
+      }
+      let solution = match solver.solve(puzzle) {
+        Ok(Some(solution)) => solution,
+        Ok(None) => return Some("no solution found".into()),
+        Err(error) => return Some(error),
+      };
+      if let Err(violation) = sudoku::validate_solution(puzzle, &solution) {
+        return Some(format!("solution {}: {}", solution, violation));
+      }
+      let several = || sudoku::count_in(puzzle, &sudoku::Geometry::classic(), 2) == Some(2);
+      if solution != expected && !several() {
+        return Some(format!("solution differs from the reference: {}", solution));
+      }
+      None
+    })
+    .collect::<Vec<Option<String>>>();
+
+  let mut mismatches = 0;
+  for (index, result) in results.iter().enumerate() {
+    if let Some(mismatch) = result {
+      println!("{}:{}: {}", filename, index + 1, mismatch);
+      mismatches += 1;
+    }
+  }
+  if references.len() > puzzles.len() {
+    println!(
+      "{}: {} reference lines without a puzzle",
+      reference,
+      references.len() - puzzles.len()
+    );
+    mismatches += 1;
+  }
+  println!("{} puzzles, {} mismatches", puzzles.len(), mismatches);
+  if mismatches > 0 {
+    1
+  } else {
+    0
+  }
+}
+
+/// The latency below which `percent` percent of the sorted `latencies` fall.
+fn percentile(latencies: &[Duration], percent: usize) -> Duration {
+  let rank = (latencies.len() * percent).div_ceil(100).max(1);
+  latencies[rank - 1]
+}
+
+/// Solves every puzzle of a file with one engine and thread count, timing each, and reports
+/// throughput, latency percentiles and the slowest puzzles. Returns the exit code.
+fn bench(args: &[String]) -> i32 {
+  let mut engine = "bitboard".to_string();
+  let mut config: Option<sudoku::SolverConfig> = None;
+  let mut threads = rayon::current_num_threads();
+  let mut json = false;
+  let mut filename = None;
+  for arg in args {
+    if let Some(name) = arg.strip_prefix("--engine=") {
+      engine = name.to_string();
+    } else if let Some(level) = arg.strip_prefix("--propagation=") {
+      match sudoku::SolverConfig::level(level) {
+        Some(level) => config = Some(level.with_branching(config.unwrap_or_default().branching)),
+        None => {
+          eprintln!(
+            "Unknown propagation level {}, expected one of {}",
+            level,
+            sudoku::SolverConfig::LEVELS.join(", ")
+          );
+          return 2;
+        }
+      }
+    } else if let Some(name) = arg.strip_prefix("--branching=") {
+      match sudoku::Branching::from_name(name) {
+        Some(branching) => config = Some(config.unwrap_or_default().with_branching(branching)),
+        None => {
+          eprintln!(
+            "Unknown branching {}, expected one of {}",
+            name,
+            sudoku::Branching::NAMES.join(", ")
+          );
+          return 2;
+        }
+      }
+    } else if let Some(count) = arg.strip_prefix("--threads=") {
+      match count.parse() {
+        Ok(count) if count > 0 => threads = count,
+        _ => {
+          eprintln!("--threads takes a positive number, not {}", count);
+          return 2;
+        }
+      }
+    } else if arg == "--json" {
+      json = true;
+    } else {
+      filename = Some(arg);
+    }
+  }
+  let Some(filename) = filename else {
+    eprintln!(
+      "Usage: sudoku bench [--engine=<name>] [--propagation=<level>] \
+       [--branching=<heuristic>] [--threads=<count>] [--json] <filename>"
+    );
+    return 2;
+  };
+  let sudokus = match fs::read_to_string(filename) {
+    Ok(sudokus) => sudokus,
+    Err(error) => {
+      eprintln!("{}: {}", filename, error);
+      return 2;
+    }
+  };
+  let puzzles = sudokus.lines().collect::<Vec<&str>>();
+  let Some(first) = puzzles.first() else {
+    eprintln!("{}: no puzzle lines", filename);
+    return 2;
+  };
+  let Some(geometry) = sudoku::Geometry::for_cells(first.len()) else {
+    eprintln!("{}:1: unsupported puzzle length {}", filename, first.len());
+    return 2;
+  };
+  let solver = match sudoku::solver::engine(&engine, &geometry, config) {
+    Ok(solver) => solver,
+    Err(error) => {
+      eprintln!("{}", error);
+      return 2;
+    }
+  };
+  let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+    Ok(pool) => pool,
+    Err(error) => {
+      eprintln!("{}", error);
+      return 2;
+    }
+  };
 
-mod sudoku;
+  let started = Instant::now();
+  let results = pool.install(|| {
+    puzzles
+      .par_iter()
+      .map(|puzzle| {
+        let started = Instant::now();
+        let solved = matches!(solver.solve(puzzle), Ok(Some(_)));
+        (started.elapsed(), solved)
+      })
+      .collect::<Vec<(Duration, bool)>>()
+  });
+  let total = started.elapsed();
+
+  let unsolved = results.iter().filter(|(_, solved)| !solved).count();
+  let mut latencies = results
+    .iter()
+    .map(|(latency, _)| *latency)
+    .collect::<Vec<Duration>>();
+  latencies.sort();
+  let mut slowest = (0..results.len()).collect::<Vec<usize>>();
+  slowest.sort_by(|a, b| results[*b].0.cmp(&results[*a].0));
+  slowest.truncate(10);
+  let per_second = puzzles.len() as f64 / total.as_secs_f64();
+  let (p50, p90, p99, max) = (
+    percentile(&latencies, 50),
+    percentile(&latencies, 90),
+    percentile(&latencies, 99),
+    latencies[latencies.len() - 1],
+  );
+  let micros = |latency: Duration| latency.as_secs_f64() * 1e6;
+
+  if json {
+    let summary = serde_json::json!({
+      "file": filename,
+      "engine": solver.name(),
+      "threads": threads,
+      "puzzles": puzzles.len(),
+      "unsolved": unsolved,
+      "total_seconds": total.as_secs_f64(),
+      "puzzles_per_second": per_second,
+      "latency_us": {
+        "p50": micros(p50),
+        "p90": micros(p90),
+        "p99": micros(p99),
+        "max": micros(max),
+      },
+      "slowest": slowest
+        .iter()
+        .map(|index| serde_json::json!({
+          "line": index + 1,
+          "puzzle": puzzles[*index],
+          "latency_us": micros(results[*index].0),
+        }))
+        .collect::<Vec<_>>(),
+    });
+    println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+  } else {
+    println!(
+      "{}: {} puzzles, {} unsolved, engine {}, {} threads",
+      filename,
+      puzzles.len(),
+      unsolved,
+      solver.name(),
+      threads
+    );
+    println!(
+      "total {:.3} s, {:.0} puzzles/s",
+      total.as_secs_f64(),
+      per_second
+    );
+    println!(
+      "latency p50 {:.1} us, p90 {:.1} us, p99 {:.1} us, max {:.1} us",
+      micros(p50),
+      micros(p90),
+      micros(p99),
+      micros(max)
+    );
+    println!("slowest:");
+    for index in slowest.iter() {
+      println!(
+        "  line {}: {:.1} us {}",
+        index + 1,
+        micros(results[*index].0),
+        puzzles[*index]
+      );
+    }
+  }
+  0
+}
+
+/// The geometry of a puzzle line: the one picked by a flag, else a Latin square or the grid
+/// the line's length implies, with the extra houses added to a 9x9 grid.
+fn line_geometry(
+  puzzle: &str,
+  geometry: Option<&sudoku::Geometry>,
+  latin: bool,
+  houses: &[u128],
+) -> Option<sudoku::Geometry> {
+  let mut geometry = match geometry {
+    Some(geometry) => Some(geometry.clone()),
+    None if latin => (1..=32)
+      .find(|size| size * size == puzzle.len())
+      .and_then(|size| sudoku::Geometry::latin(size).ok()),
+    None => sudoku::Geometry::for_cells(puzzle.len()),
+  };
+  if let Some(geometry) = geometry.as_mut().filter(|geometry| geometry.cells == 81) {
+    for house in houses {
+      geometry.add_house(&variant::cells(*house)).ok()?;
+    }
+  }
+  geometry
+}
 
 fn main() {
-  let filename = match env::args().nth(1) {
+  let args = env::args().skip(1).collect::<Vec<String>>();
+  if args.first().map(String::as_str) == Some("bench") {
+    process::exit(bench(&args[1..]));
+  }
+  if args.first().map(String::as_str) == Some("verify") {
+    match &args[1..] {
+      [filename] => process::exit(verify(filename, None)),
+      [filename, reference] => process::exit(verify(filename, Some(reference))),
+      _ => {
+        eprintln!("Usage: sudoku verify <puzzles.sudokus> [<reference.solved>]");
+        process::exit(2);
+      }
+    }
+  }
+
+  let mut variant = sudoku::Variant::new();
+  let mut houses = vec![];
+  let mut non_consecutive = false;
+  let mut sandwiches = vec![];
+  let mut engine = None;
+  let mut geometry = None;
+  let mut description = false;
+  let mut fpuzzles = false;
+  let mut to_fpuzzles = false;
+  let mut to_dimacs = false;
+  let mut sat_model = None;
+  let mut latin = false;
+  let mut count = false;
+  let mut config = sudoku::SolverConfig::default();
+  let mut tuned = false;
+  let mut branching = None;
+  let mut filename = None;
+  for arg in args {
+    if let Some(level) = arg.strip_prefix("--propagation=") {
+      tuned = true;
+      config = sudoku::SolverConfig::level(level).unwrap_or_else(|| {
+        eprintln!(
+          "Unknown propagation level {}, expected one of {}",
+          level,
+          sudoku::SolverConfig::LEVELS.join(", ")
+        );
+        process::exit(1);
+      });
+      continue;
+    }
+    if let Some(clues) = arg.strip_prefix("--sandwiches=") {
+      sandwiches = constraint::Sandwich::parse(clues).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+      });
+      continue;
+    }
+    if let Some(path) = arg.strip_prefix("--sat-model=") {
+      sat_model = Some(fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("{}: {}", path, error);
+        process::exit(1);
+      }));
+      continue;
+    }
+    if let Some(name) = arg.strip_prefix("--engine=") {
+      if !sudoku::solver::ENGINES.contains(&name) {
+        eprintln!(
+          "Unknown engine {}, expected one of {}",
+          name,
+          sudoku::solver::ENGINES.join(", ")
+        );
+        process::exit(1);
+      }
+      engine = Some(name.to_string());
+      continue;
+    }
+    if let Some(name) = arg.strip_prefix("--branching=") {
+      branching = Some(sudoku::Branching::from_name(name).unwrap_or_else(|| {
+        eprintln!(
+          "Unknown branching {}, expected one of {}",
+          name,
+          sudoku::Branching::NAMES.join(", ")
+        );
+        process::exit(1);
+      }));
+      continue;
+    }
+    match arg.as_str() {
+      "--non-consecutive" => non_consecutive = true,
+      "--windoku" => houses.extend(variant::windoku()),
+      "--disjoint-groups" => houses.extend(variant::disjoint_groups()),
+      "--twodoku" => geometry = Some(sudoku::Geometry::twodoku()),
+      "--butterfly" => geometry = Some(sudoku::Geometry::butterfly()),
+      "--samurai" => geometry = Some(sudoku::Geometry::samurai()),
+      "--latin" => latin = true,
+      "--count" => count = true,
+      "--description" => description = true,
+      "--fpuzzles" => fpuzzles = true,
+      "--to-fpuzzles" => to_fpuzzles = true,
+      "--to-dimacs" => to_dimacs = true,
+      _ => filename = Some(arg),
+    }
+  }
+
+  if let Some(branching) = branching {
+    config = config.with_branching(branching);
+    tuned = true;
+  }
+  let constrained = non_consecutive || !sandwiches.is_empty();
+  if non_consecutive {
+    variant.add(constraint::non_consecutive(9));
+  }
+  for house in houses.iter() {
+    if let Err(error) = variant.add_house(*house) {
+      eprintln!("{}", error);
+      process::exit(1);
+    }
+  }
+  if (to_dimacs || sat_model.is_some()) && (count || engine.is_some()) {
+    eprintln!("--to-dimacs and --sat-model take no --count or --engine");
+    process::exit(1);
+  }
+  if count && constrained {
+    eprintln!("--count takes extra houses but no other constraints on puzzle lines");
+    process::exit(1);
+  }
+  if engine.is_some() && (constrained || count || description || fpuzzles) {
+    eprintln!("--engine only solves puzzle lines, with extra houses but no other constraints");
+    process::exit(1);
+  }
+  let tuning = tuned.then_some(config);
+  if let Some(Err(error)) = engine
+    .as_ref()
+    .map(|name| sudoku::solver::engine(name, &sudoku::Geometry::classic(), tuning))
+  {
+    eprintln!("{}", error);
+    process::exit(1);
+  }
+
+  let filename = match filename {
     Some(filename) => filename,
     None => {
-      println!("Usage: sudoku <filename>");
+      println!(
+        "Usage: sudoku [--non-consecutive] [--sandwiches=<clues>] [--windoku] [--disjoint-groups] \
+         [--twodoku|--butterfly|--samurai] \
+         [--propagation=<level>] [--branching=<heuristic>] <filename>\n       \
+         sudoku --engine=<name> [--windoku] [--disjoint-groups] \
+         [--propagation=<level>] [--branching=<heuristic>] [--latin|--twodoku|--butterfly|--samurai] <filename>\n       \
+         sudoku [--count] [--latin|--twodoku|--butterfly|--samurai] <filename>\n       \
+         sudoku [--to-dimacs|--sat-model=<file>] [--non-consecutive] [--sandwiches=<clues>] [--windoku] \
+         [--disjoint-groups] [--latin|--twodoku|--butterfly|--samurai] <one-puzzle-file>\n       \
+         sudoku [--count|--to-fpuzzles|--to-dimacs|--sat-model=<file>] --description <puzzle.json>\n       \
+         sudoku [--count|--to-fpuzzles|--to-dimacs|--sat-model=<file>] --fpuzzles <puzzle.txt>\n       \
+         sudoku verify <puzzles.sudokus> [<reference.solved>]\n       \
+         sudoku bench [--engine=<name>] [--propagation=<level>] [--branching=<heuristic>] \
+         [--threads=<count>] [--json] <filename>"
+      );
       return;
     }
   };
 
-  let sudokus = fs::read_to_string(filename).unwrap();
+  let sudokus = fs::read_to_string(&filename).unwrap();
 
-  print!(
-    "{}",
-    sudokus
-      .lines()
-      .collect::<Vec<&str>>()
-      .par_iter()
-      .map(|puzzle| format!("{},{}", *puzzle, sudoku::solve(*puzzle)))
-      .collect::<Vec<String>>()
-      .join("\n")
-  );
+  if description || fpuzzles {
+    let description = if fpuzzles {
+      sudoku::fpuzzles::import(&sudokus)
+    } else {
+      sudoku::Description::from_json(&sudokus)
+    };
+    let result = description.and_then(|description| {
+      let model = description.model()?;
+      if to_dimacs {
+        return sudoku::dimacs::export(&model);
+      }
+      if let Some(text) = &sat_model {
+        let solution = sudoku::dimacs::read_model(text, &model)?;
+        return Ok(format!("{},{}", model.puzzle(), solution));
+      }
+      if count {
+        return Ok(format!("{},{}", model.puzzle(), model.count(usize::MAX)));
+      }
+      let solution = model.solve();
+      if to_fpuzzles {
+        sudoku::fpuzzles::export_compressed(&description, solution.as_deref())
+      } else {
+        Ok(format!(
+          "{},{}",
+          model.puzzle(),
+          solution.unwrap_or_default()
+        ))
+      }
+    });
+    match result {
+      Ok(output) => print!("{}", output),
+      Err(error) => {
+        eprintln!("{}", error);
+        process::exit(1);
+      }
+    }
+    return;
+  }
+
+  if to_dimacs || sat_model.is_some() {
+    let lines = sudokus.lines().collect::<Vec<&str>>();
+    let [puzzle] = lines[..] else {
+      eprintln!("--to-dimacs and --sat-model take a file with one puzzle line");
+      process::exit(1);
+    };
+    let result = line_geometry(puzzle, geometry.as_ref(), latin, &houses)
+      .ok_or_else(|| format!("unsupported puzzle length {}", puzzle.len()))
+      .and_then(|geometry| {
+        if constrained && geometry.cells != 81 {
+          return Err("--non-consecutive and --sandwiches take a 9x9 puzzle".to_string());
+        }
+        let mut model = sudoku::Model::new(geometry);
+        model.givens = model.geometry.parse(puzzle).ok_or("invalid puzzle")?;
+        if non_consecutive {
+          model.add(constraint::non_consecutive(9));
+        }
+        for sandwich in sandwiches {
+          model.add(sandwich);
+        }
+        match &sat_model {
+          Some(text) => sudoku::dimacs::read_model(text, &model)
+            .map(|solution| format!("{},{}", puzzle, solution)),
+          None => sudoku::dimacs::export(&model),
+        }
+      });
+    match result {
+      Ok(output) => print!("{}", output),
+      Err(error) => {
+        eprintln!("{}", error);
+        process::exit(1);
+      }
+    }
+    return;
+  }
+
+  for sandwich in sandwiches {
+    variant.add(sandwich);
+  }
+  let results = sudokus
+    .lines()
+    .collect::<Vec<&str>>()
+    .par_iter()
+    .enumerate()
+    .map(|(index, puzzle)| {
+      let geometry =
+        if geometry.is_some() || latin || count || engine.is_some() || puzzle.len() != 81 {
+          line_geometry(puzzle, geometry.as_ref(), latin, &houses)
+        } else {
+          None
+        };
+      if let Some(name) = &engine {
+        // A line the engine cannot take fails the run rather than printing no solution.
+        let solution = geometry
+          .as_ref()
+          .ok_or_else(|| format!("unsupported puzzle length {}", puzzle.len()))
+          .and_then(|geometry| sudoku::solver::engine(name, geometry, tuning))
+          .and_then(|solver| solver.solve(puzzle))
+          .map_err(|error| format!("{}:{}: {}", filename, index + 1, error))?;
+        return Ok(format!("{},{}", *puzzle, solution.unwrap_or_default()));
+      }
+      Ok(format!(
+        "{},{}",
+        *puzzle,
+        match &geometry {
+          Some(geometry) if count =>
+            sudoku::count_in(puzzle, geometry, usize::MAX).map(|solutions| solutions.to_string()),
+          Some(geometry) => sudoku::solve_in(puzzle, geometry),
+          None if puzzle.len() == 81 => sudoku::solve_with_config(puzzle, &variant, &config),
+          None => None,
+        }
+        .unwrap_or_default()
+      ))
+    })
+    .collect::<Result<Vec<String>, String>>();
+  match results {
+    Ok(lines) => print!("{}", lines.join("\n")),
+    Err(error) => {
+      eprintln!("{}", error);
+      process::exit(1);
+    }
+  }
 }
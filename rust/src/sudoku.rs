@@ -1,12 +1,41 @@
+pub mod band;
+pub mod bits;
+pub mod config;
+pub mod constraint;
+pub mod deduction;
+pub mod description;
+pub mod dimacs;
+pub mod dlx;
+pub mod fpuzzles;
+pub mod geometry;
 pub mod list;
+pub mod lz_string;
+pub mod model;
+pub mod search;
+pub mod solver;
+pub mod tables;
+pub mod validate;
+pub mod variant;
+mod wide;
+
+pub use config::{Branching, SolverConfig};
+pub use deduction::Stages;
+pub use description::Description;
+pub use geometry::Geometry;
+pub use model::Model;
+pub use search::{CancelToken, Limits, Outcome, Search, Stats, Status};
+pub use solver::Solver;
+pub use validate::{validate_partial, validate_solution, Conflict, Violation};
+pub use variant::Variant;
 
 const BIT9: [usize; 9] = list::BIT9;
-const GROUPS: [u128; 27] = list::GROUPS;
-const SET_CELLS: [u128; 81] = list::SET_CELLS;
-const POSSIBLE: [[usize; 937]; 54] = list::POSSIBLE;
 const BIT81: [u128; 81] = list::BIT81;
 const ALL81: u128 = list::ALL81;
 
+/// Below this many candidates a digit is checked against the band patterns; above it the
+/// check rarely removes anything.
+const BAND_CHECK_BELOW: u32 = 30;
+
 fn get_bits_list(bits: usize) -> Vec<usize> {
     BIT9.iter()
         .enumerate()
@@ -30,15 +59,24 @@ lazy_static! {
     pub static ref BITS_LISTS: [Vec<usize>; 512] = get_bits_lists();
 }
 
-struct Board {
+struct Board<'a> {
+    variant: &'a Variant,
+    config: SolverConfig,
     is_sudoku: bool,
     numbers: usize,
     number_cells: [u128; 9],
 }
 
-impl Board {
-    fn new(cell_values: [usize; 81]) -> String {
+impl<'a> Board<'a> {
+    /// The board with the givens propagated, and the digit to place first.
+    fn new(
+        cell_values: [usize; 81],
+        variant: &'a Variant,
+        config: SolverConfig,
+    ) -> (Self, (usize, u32)) {
         let mut board = Board {
+            variant,
+            config,
             is_sudoku: true,
             numbers: 0b111111111,
             number_cells: [ALL81; 9],
@@ -46,87 +84,203 @@ impl Board {
 
         let mut remove_from_others = [0; 9];
 
-        for cell_index in 0..81 {
-            let value = cell_values[cell_index];
-            if value > 0 {
+        for (cell_index, value) in cell_values.iter().enumerate() {
+            if *value > 0 {
                 let number = value - 1;
-                board.number_cells[number] &= SET_CELLS[cell_index];
+                board.number_cells[number] &= variant.set_cells[cell_index];
                 remove_from_others[number] |= BIT81[cell_index];
             }
         }
 
-        let shortest = board.remove_from_others(remove_from_others);
-        board.trial_and_error(shortest);
-        assert!(board.is_sudoku);
-
+        let mut keep = Self::others_kept(remove_from_others);
+        for (keep, mask) in keep.iter_mut().zip(variant.number_masks.iter()) {
+            *keep &= mask;
+        }
+        let shortest = board.keep_cells(keep);
+        (board, shortest)
+    }
+    fn solution(&self) -> Option<String> {
+        if !self.is_sudoku {
+            return None;
+        }
         let mut solved: [u32; 81] = [0; 81];
-        for (number, cells) in board.number_cells.iter_mut().enumerate() {
+        for (number, cells) in self.number_cells.iter().enumerate() {
+            let mut cells = *cells;
             let mut cell_index = 0;
-            while *cells != 0 {
+            while cells != 0 {
                 let tz = cells.trailing_zeros() as usize;
                 cell_index += tz;
                 solved[cell_index] = (number + 1) as u32;
-                *cells >>= tz + 1;
+                cells >>= tz + 1;
                 cell_index += 1;
             }
         }
-        solved
-            .iter()
-            .map(|n| char::from_digit(*n, 10).unwrap())
-            .collect()
+        Some(
+            solved
+                .iter()
+                .map(|n| char::from_digit(*n, 10).unwrap())
+                .collect(),
+        )
     }
     fn remove_from_others(&mut self, remove_from_others: [u128; 9]) -> (usize, u32) {
-        let mut shortest_length = 81;
-        let mut shortest_number = 0;
-        let mut new_remove_from_others = [0; 9];
-        let mut new_remove = false;
-        for number in BITS_LISTS.get(self.numbers).unwrap().iter() {
-            let mut union = 0;
+        self.keep_cells(Self::others_kept(remove_from_others))
+    }
+    fn others_kept(remove_from_others: [u128; 9]) -> [u128; 9] {
+        let mut keep = [ALL81; 9];
+        for (number, keep) in keep.iter_mut().enumerate() {
             for (remove_number, remove) in remove_from_others.iter().enumerate() {
-                if *number != remove_number {
-                    union |= *remove;
+                if number != remove_number {
+                    *keep &= !*remove;
                 }
             }
-            let cells = self.number_cells.get_mut(*number).unwrap();
-            let removed = *cells & !union;
-            if removed != *cells {
-                *cells = removed;
-                let ones = cells.count_ones();
-                if ones < 9 {
-                    self.is_sudoku = false;
-                    return (0, 0);
-                }
-                for group_mask in GROUPS.iter() {
-                    let group = *cells & *group_mask;
-                    let group_ones = group.count_ones();
-                    if group_ones == 0 {
+        }
+        keep
+    }
+    /// Keeps only the given cells of each digit and propagates until nothing changes, then
+    /// picks the digit to place next.
+    fn keep_cells(&mut self, mut keep: [u128; 9]) -> (usize, u32) {
+        loop {
+            let mut shortest_length = 81;
+            let mut shortest_number = 0;
+            let mut new_remove_from_others = [0; 9];
+            let mut new_remove = false;
+            for number in BITS_LISTS.get(self.numbers).unwrap().iter() {
+                let cells = self.number_cells.get_mut(*number).unwrap();
+                let removed = *cells & keep[*number];
+                if removed != *cells {
+                    *cells = removed;
+                    if self.config.band_patterns && cells.count_ones() < BAND_CHECK_BELOW {
+                        *cells = band::keep_band_patterns(*cells);
+                    }
+                    let ones = cells.count_ones();
+                    if ones < 9 {
                         self.is_sudoku = false;
                         return (0, 0);
-                    } else if group_ones == 1 {
-                        let set_cells = *cells & SET_CELLS[group.trailing_zeros() as usize];
-                        if set_cells != *cells {
-                            *cells = set_cells;
-                            new_remove_from_others[*number] |= group;
-                            new_remove = true;
+                    }
+                    for group_mask in self.variant.groups.iter() {
+                        let group = *cells & *group_mask;
+                        let group_ones = group.count_ones();
+                        if group_ones == 0 {
+                            self.is_sudoku = false;
+                            return (0, 0);
+                        } else if group_ones == 1 && self.config.singles {
+                            let set_cells =
+                                *cells & self.variant.set_cells[group.trailing_zeros() as usize];
+                            if set_cells != *cells {
+                                *cells = set_cells;
+                                new_remove_from_others[*number] |= group;
+                                new_remove = true;
+                            }
                         }
                     }
+                    if ones < shortest_length {
+                        shortest_number = *number;
+                        shortest_length = ones;
+                    }
+                } else {
+                    let ones = cells.count_ones();
+                    if ones < shortest_length {
+                        shortest_number = *number;
+                        shortest_length = ones;
+                    }
                 }
-                if ones < shortest_length {
-                    shortest_number = *number;
-                    shortest_length = ones;
-                }
+            }
+            keep = if new_remove {
+                Self::others_kept(new_remove_from_others)
             } else {
-                let ones = cells.count_ones();
-                if ones < shortest_length {
-                    shortest_number = *number;
-                    shortest_length = ones;
+                match self.propagate_constraints().or_else(|| self.deduce()) {
+                    Some(keep) => keep,
+                    None if self.is_sudoku => {
+                        return self.branch((shortest_number, shortest_length))
+                    }
+                    None => return (0, 0),
+                }
+            };
+        }
+    }
+    fn propagate_constraints(&mut self) -> Option<[u128; 9]> {
+        if self.variant.constraints.is_empty() {
+            return None;
+        }
+        let mut candidates = [0u32; 81];
+        for (number, cells) in self.number_cells.iter().enumerate() {
+            let mut cells = *cells;
+            while cells != 0 {
+                candidates[cells.trailing_zeros() as usize] |= 1 << number;
+                cells &= cells - 1;
+            }
+        }
+        let original = candidates;
+        loop {
+            let before = candidates;
+            for constraint in self.variant.constraints.iter() {
+                if !constraint.propagate(&mut candidates) {
+                    self.is_sudoku = false;
+                    return None;
+                }
+            }
+            if before == candidates {
+                break;
+            }
+        }
+        if candidates == original {
+            return None;
+        }
+        let mut keep = [ALL81; 9];
+        for (cell_index, (after, before)) in candidates.iter().zip(original.iter()).enumerate() {
+            let mut removed = before & !after;
+            while removed != 0 {
+                let number = removed.trailing_zeros() as usize;
+                if self.numbers & BIT9[number] == 0 {
+                    self.is_sudoku = false;
+                    return None;
                 }
+                keep[number] &= !BIT81[cell_index];
+                removed &= removed - 1;
             }
         }
-        if new_remove {
-            self.remove_from_others(new_remove_from_others)
-        } else {
-            (shortest_number, shortest_length)
+        Some(keep)
+    }
+    /// The cells left by the configured stages, if they remove any.
+    fn deduce(&mut self) -> Option<[u128; 9]> {
+        if !self.is_sudoku || self.config.stages.is_empty() {
+            return None;
+        }
+        let Some(kept) = deduction::deduce(
+            &self.config.stages,
+            &self.variant.groups,
+            self.numbers,
+            &self.number_cells,
+        ) else {
+            self.is_sudoku = false;
+            return None;
+        };
+        if kept == self.number_cells {
+            return None;
+        }
+        Some(kept)
+    }
+    /// The digit to place next and its number of candidates, given the digit with the fewest.
+    fn branch(&self, shortest: (usize, u32)) -> (usize, u32) {
+        let numbers = BITS_LISTS[self.numbers].iter();
+        let with_length = |number: &usize| (*number, self.number_cells[*number].count_ones());
+        match self.config.branching {
+            Branching::FewestCandidates => shortest,
+            Branching::FirstDigit => numbers.map(with_length).next().unwrap_or(shortest),
+            Branching::FewestPatterns => numbers
+                .map(with_length)
+                .min_by_key(|(number, length)| {
+                    if *length == 9 {
+                        return 0;
+                    }
+                    let cells = self.number_cells[*number];
+                    (0..3)
+                        .map(|band| {
+                            band::band_patterns((cells >> (band * 27)) as usize & band::ALL27).len()
+                        })
+                        .product::<usize>()
+                })
+                .unwrap_or(shortest),
         }
     }
     fn remove_single_from_others(&mut self, number: usize, cells: u128) -> (usize, u32) {
@@ -134,74 +288,67 @@ impl Board {
         remove_from_others[number] = cells;
         self.remove_from_others(remove_from_others)
     }
-    fn trial_and_error(&mut self, shortest: (usize, u32)) {
-        if self.is_sudoku == false {
-            return;
-        }
+    fn fits_houses(&self, cells: u128) -> bool {
+        self.variant
+            .houses
+            .iter()
+            .all(|house| (cells & house).count_ones() == 1)
+    }
+    /// Whether nine cells left to a digit already hold one per group.
+    fn is_placement(&self, cells: u128) -> bool {
+        list::GROUPS
+            .iter()
+            .all(|group| (cells & group).count_ones() == 1)
+            && self.fits_houses(cells)
+    }
+}
+
+pub fn solve(puzzle: &str) -> String {
+    let solution = if puzzle.len() == 81 {
+        solve_with(puzzle, &Variant::default())
+    } else {
+        let geometry = Geometry::for_cells(puzzle.len());
+        assert!(geometry.is_some());
+        solve_in(puzzle, &geometry.unwrap())
+    };
+    assert!(solution.is_some());
+    solution.unwrap()
+}
 
-        let (number, length) = shortest;
+pub fn solve_in(puzzle: &str, geometry: &Geometry) -> Option<String> {
+    let values = geometry.parse(puzzle)?;
+    let mut board = wide::WideBoard::new(geometry, &[], &values);
+    if board.solve() {
+        Some(geometry.format(&board.values()))
+    } else {
+        None
+    }
+}
 
-        self.numbers ^= BIT9[number];
-        let cells = self.number_cells[number];
+/// The number of solutions of `puzzle` in `geometry`, counting no further than `limit`.
+pub fn count_in(puzzle: &str, geometry: &Geometry, limit: usize) -> Option<usize> {
+    let values = geometry.parse(puzzle)?;
+    Some(wide::WideBoard::new(geometry, &[], &values).count(limit))
+}
 
-        if length == 9 {
-            if self.numbers != 0 {
-                let shortest = self.remove_single_from_others(number, cells);
-                self.trial_and_error(shortest);
-            }
-            return;
-        }
+pub fn solve_with(puzzle: &str, variant: &Variant) -> Option<String> {
+    solve_with_config(puzzle, variant, &SolverConfig::default())
+}
 
-        let numbers = self.numbers;
-        let number_cells = self.number_cells.clone();
-
-        let first_group = (cells & 0b111111111111111111) as usize;
-        let second_group = ((cells >> 18) & 0b111111111111111111111111111) as usize;
-        let third_group = ((cells >> 45) & 0b111111111111111111111111111111111111) as usize;
-
-        for sub_list in POSSIBLE.iter() {
-            let first = sub_list[0];
-            if first & first_group == first {
-                for index in (1..925).step_by(13) {
-                    let second = sub_list[index];
-                    if second & second_group == second {
-                        for index in index + 1..index + 13 {
-                            let third = sub_list[index];
-                            if third & third_group == third {
-                                self.number_cells[number] =
-                                    (third as u128) << 45 | (second as u128) << 18 | first as u128;
-                                self.is_sudoku = true;
-                                if self.numbers == 0 {
-                                    return;
-                                }
-                                let shortest = self
-                                    .remove_single_from_others(number, self.number_cells[number]);
-                                self.trial_and_error(shortest);
-                                if self.is_sudoku {
-                                    return;
-                                } else {
-                                    self.numbers = numbers;
-                                    self.number_cells = number_cells.clone();
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        self.is_sudoku = false;
-    }
+/// Solves like `solve_with`, propagating and branching as `config` says.
+pub fn solve_with_config(puzzle: &str, variant: &Variant, config: &SolverConfig) -> Option<String> {
+    let mut search = Search::new(puzzle, variant, *config).ok()?;
+    search.run(usize::MAX);
+    search.solution()
 }
 
-pub fn solve(puzzle: &str) -> String {
-    assert_eq!(puzzle.len(), 81);
-    let mut cell_values = [0; 81];
-    for (index, value) in puzzle
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as usize)
-        .enumerate()
-    {
-        cell_values[index] = value;
-    }
-    Board::new(cell_values)
+/// Solves a classic puzzle within `limits`, giving up with the work done so far.
+pub fn solve_with_limits(puzzle: &str, limits: Limits) -> Outcome {
+    let variant = Variant::default();
+    let mut search = match Search::new(puzzle, &variant, SolverConfig::default()) {
+        Ok(search) => search.with_limits(limits),
+        Err(error) => return Outcome::Invalid(error),
+    };
+    search.run(usize::MAX);
+    search.outcome().unwrap()
 }
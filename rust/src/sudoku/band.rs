@@ -0,0 +1,274 @@
+//! Band patterns: the ways one digit fits in a band of three rows, one cell per row and box.
+
+use super::list::POSSIBLE;
+
+pub const ALL27: usize = (1 << 27) - 1;
+
+/// The 162 band patterns as 27-bit masks, read off the top band of every `POSSIBLE` placement.
+fn valid_band_cells() -> Vec<usize> {
+    let mut patterns = vec![];
+    for sub_list in POSSIBLE.iter() {
+        for index in (1..925).step_by(13) {
+            let band = (sub_list[index] << 18 | sub_list[0]) & ALL27;
+            if !patterns.contains(&band) {
+                patterns.push(band);
+            }
+        }
+    }
+    patterns
+}
+
+/// For each row of a band and each 9-bit mask of that row, the union of the band patterns
+/// whose cell in the row lies within the mask.
+fn row_bands_union(patterns: &[usize]) -> [[usize; 512]; 3] {
+    let mut unions = [[0; 512]; 3];
+    for (row, unions) in unions.iter_mut().enumerate() {
+        for (mask, union) in unions.iter_mut().enumerate() {
+            for pattern in patterns.iter() {
+                if (pattern >> (row * 9)) & 511 & !mask == 0 {
+                    *union |= pattern;
+                }
+            }
+        }
+    }
+    unions
+}
+
+/// A set of band patterns, by their index in `VALID_BAND_CELLS`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Patterns([u64; 3]);
+
+impl Patterns {
+    fn insert(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn and(&self, other: &Patterns) -> Patterns {
+        Patterns([
+            self.0[0] & other.0[0],
+            self.0[1] & other.0[1],
+            self.0[2] & other.0[2],
+        ])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 3]
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// The first pattern at or after `index`.
+    fn first_from(&self, index: usize) -> Option<usize> {
+        (index / 64..3).find_map(|word| {
+            let mut bits = self.0[word];
+            if word == index / 64 {
+                bits &= !0 << (index % 64);
+            }
+            (bits != 0).then(|| word * 64 + bits.trailing_zeros() as usize)
+        })
+    }
+}
+
+/// For each row of a band and each 9-bit mask of that row, the band patterns whose cell in the
+/// row lies within the mask.
+fn row_bands(patterns: &[usize]) -> [[Patterns; 512]; 3] {
+    let mut sets = [[Patterns::default(); 512]; 3];
+    for (row, sets) in sets.iter_mut().enumerate() {
+        for (mask, set) in sets.iter_mut().enumerate() {
+            for (index, pattern) in patterns.iter().enumerate() {
+                if (pattern >> (row * 9)) & 511 & !mask == 0 {
+                    set.insert(index);
+                }
+            }
+        }
+    }
+    sets
+}
+
+/// For each band pattern, the patterns using none of its columns, which can sit in another
+/// band of the same digit.
+fn column_compatible(patterns: &[usize]) -> Vec<Patterns> {
+    let columns = |pattern: usize| (pattern | pattern >> 9 | pattern >> 18) & 511;
+    patterns
+        .iter()
+        .map(|first| {
+            let mut set = Patterns::default();
+            for (index, second) in patterns.iter().enumerate() {
+                if columns(*first) & columns(*second) == 0 {
+                    set.insert(index);
+                }
+            }
+            set
+        })
+        .collect()
+}
+
+lazy_static! {
+    pub static ref VALID_BAND_CELLS: Vec<usize> = valid_band_cells();
+    pub static ref ROW_BANDS_UNION: [[usize; 512]; 3] = row_bands_union(&VALID_BAND_CELLS);
+    pub static ref ROW_BANDS: [[Patterns; 512]; 3] = row_bands(&VALID_BAND_CELLS);
+    pub static ref COLUMN_COMPATIBLE: Vec<Patterns> = column_compatible(&VALID_BAND_CELLS);
+}
+
+/// The band patterns which fit within the 27 cells of a band.
+pub fn band_patterns(band: usize) -> Patterns {
+    let sets = &*ROW_BANDS;
+    sets[0][band & 511]
+        .and(&sets[1][(band >> 9) & 511])
+        .and(&sets[2][(band >> 18) & 511])
+}
+
+/// The placements of one digit within its candidate cells, a fitting band pattern in each band
+/// using every column once. Only the last position is kept, so a search can stop between
+/// placements and pick up again.
+#[derive(Clone, Copy, Debug)]
+pub struct Placements {
+    bands: [Patterns; 3],
+    next: [usize; 3],
+}
+
+impl Placements {
+    pub fn new(cells: u128) -> Self {
+        Placements {
+            bands: [0, 1, 2].map(|band| band_patterns((cells >> (band * 27)) as usize & ALL27)),
+            next: [0; 3],
+        }
+    }
+}
+
+impl Iterator for Placements {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        let compatible = &*COLUMN_COMPATIBLE;
+        let [mut first, mut second, mut third] = self.next;
+        loop {
+            match self.bands[0].first_from(first) {
+                Some(found) if found == first => {}
+                Some(found) => [first, second, third] = [found, 0, 0],
+                None => {
+                    self.next = [first, 0, 0];
+                    return None;
+                }
+            }
+            match self.bands[1].and(&compatible[first]).first_from(second) {
+                Some(found) if found == second => {}
+                Some(found) => [second, third] = [found, 0],
+                None => {
+                    [first, second, third] = [first + 1, 0, 0];
+                    continue;
+                }
+            }
+            let thirds = self.bands[2]
+                .and(&compatible[first])
+                .and(&compatible[second]);
+            match thirds.first_from(third) {
+                Some(found) => {
+                    self.next = [first, second, found + 1];
+                    let patterns = &*VALID_BAND_CELLS;
+                    return Some(
+                        (patterns[found] as u128) << 54
+                            | (patterns[second] as u128) << 27
+                            | patterns[first] as u128,
+                    );
+                }
+                None => [second, third] = [second + 1, 0],
+            }
+        }
+    }
+}
+
+/// Drops the cells of one digit which, for some row of their band, no band pattern fitting
+/// that row's cells can reach. Cells surviving this may still lie on no pattern fitting the
+/// whole band; every cell such a pattern reaches is kept.
+pub fn keep_band_patterns(cells: u128) -> u128 {
+    let unions = &*ROW_BANDS_UNION;
+    let mut kept = 0;
+    for band in 0..3 {
+        let rows = (cells >> (band * 27)) as usize & ALL27;
+        let union =
+            unions[0][rows & 511] & unions[1][(rows >> 9) & 511] & unions[2][(rows >> 18) & 511];
+        kept |= ((rows & union) as u128) << (band * 27);
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::list::ALL81;
+    use super::*;
+
+    /// The cells of `cells` lying on a band pattern which fits within its band, by brute force.
+    fn reachable(cells: u128) -> u128 {
+        let mut reached = 0;
+        for band in 0..3 {
+            let rows = (cells >> (band * 27)) as usize & ALL27;
+            for pattern in VALID_BAND_CELLS
+                .iter()
+                .filter(|pattern| *pattern & !rows == 0)
+            {
+                reached |= (*pattern as u128) << (band * 27);
+            }
+        }
+        reached
+    }
+
+    /// Masks of 81 cells from a fixed xorshift sequence, each cell kept with probability 1/2,
+    /// then 3/4.
+    fn masks() -> impl Iterator<Item = u128> {
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u128
+        };
+        (0..2000).map(move |index| {
+            let mut cells = next() | next() << 64;
+            if index % 2 == 1 {
+                cells |= next() | next() << 64;
+            }
+            cells & ALL81
+        })
+    }
+
+    #[test]
+    fn places_every_digit_layout() {
+        assert_eq!(VALID_BAND_CELLS.len(), 162);
+        let placements = Placements::new(ALL81).collect::<Vec<u128>>();
+        assert_eq!(placements.len(), 46656);
+        let mut sorted = placements.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 46656);
+        for placement in placements.iter() {
+            assert_eq!(placement.count_ones(), 9);
+            assert_eq!(reachable(*placement), *placement);
+        }
+    }
+
+    #[test]
+    fn places_within_candidates() {
+        for cells in masks().take(200) {
+            for placement in Placements::new(cells) {
+                assert_eq!(placement & !cells, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_every_reachable_cell() {
+        assert_eq!(keep_band_patterns(ALL81), ALL81);
+        assert_eq!(keep_band_patterns(0), 0);
+        // With the top row's candidates in the first box, that box's other rows lose theirs.
+        let cells = ALL81 & !(0b111111000 | 0b111 << 9 | 0b111 << 18);
+        assert_eq!(keep_band_patterns(ALL81 & !0b111111000), cells);
+        for cells in masks() {
+            let kept = keep_band_patterns(cells);
+            assert_eq!(kept & !cells, 0);
+            assert_eq!(reachable(cells) & !kept, 0);
+        }
+    }
+}
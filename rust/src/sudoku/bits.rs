@@ -0,0 +1,93 @@
+const WORDS: usize = 10;
+
+/// A bitboard over up to 640 cells, for grids where 81 bits no longer fit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Bits([u64; WORDS]);
+
+impl Bits {
+    pub const CAPACITY: usize = WORDS * 64;
+
+    pub fn empty() -> Self {
+        Bits([0; WORDS])
+    }
+
+    pub fn all(cells: usize) -> Self {
+        let mut bits = Bits::empty();
+        for cell in 0..cells {
+            bits.set(cell);
+        }
+        bits
+    }
+
+    pub fn single(cell: usize) -> Self {
+        let mut bits = Bits::empty();
+        bits.set(cell);
+        bits
+    }
+
+    pub fn set(&mut self, cell: usize) {
+        self.0[cell / 64] |= 1 << (cell % 64);
+    }
+
+    pub fn clear(&mut self, cell: usize) {
+        self.0[cell / 64] &= !(1 << (cell % 64));
+    }
+
+    pub fn has(&self, cell: usize) -> bool {
+        self.0[cell / 64] & (1 << (cell % 64)) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    pub fn first(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(index, word)| index * 64 + word.trailing_zeros() as usize)
+    }
+
+    pub fn and(&self, other: &Bits) -> Bits {
+        let mut bits = *self;
+        for (word, other) in bits.0.iter_mut().zip(other.0.iter()) {
+            *word &= other;
+        }
+        bits
+    }
+
+    pub fn or(&self, other: &Bits) -> Bits {
+        let mut bits = *self;
+        for (word, other) in bits.0.iter_mut().zip(other.0.iter()) {
+            *word |= other;
+        }
+        bits
+    }
+
+    pub fn and_not(&self, other: &Bits) -> Bits {
+        let mut bits = *self;
+        for (word, other) in bits.0.iter_mut().zip(other.0.iter()) {
+            *word &= !other;
+        }
+        bits
+    }
+
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(index, word)| {
+            let mut word = *word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(index * 64 + bit)
+            })
+        })
+    }
+}
@@ -0,0 +1,103 @@
+//! How the classic solver propagates and branches, for comparing profiles per workload.
+
+use super::deduction::Stages;
+
+/// Which digit the search places next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Branching {
+    /// The digit with the fewest candidate cells.
+    #[default]
+    FewestCandidates,
+    /// The digit with the fewest combinations of fitting band patterns.
+    FewestPatterns,
+    /// The lowest digit not yet placed.
+    FirstDigit,
+}
+
+impl Branching {
+    pub const NAMES: [&'static str; 3] = ["fewest-candidates", "fewest-patterns", "first-digit"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fewest-candidates" => Some(Branching::FewestCandidates),
+            "fewest-patterns" => Some(Branching::FewestPatterns),
+            "first-digit" => Some(Branching::FirstDigit),
+            _ => None,
+        }
+    }
+}
+
+/// The deductions run before each branch, and the branching heuristic.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SolverConfig {
+    /// Hidden singles: a digit with one cell left in a group is placed there.
+    pub singles: bool,
+    /// Stages run once singles stall.
+    pub stages: Stages,
+    /// Digits with few candidates drop the cells no band pattern reaches.
+    pub band_patterns: bool,
+    pub branching: Branching,
+}
+
+/// Singles and band patterns without stages, which is none of the `LEVELS`: the stages cost
+/// more time than the nodes they save on the forum and benchmark sets, while band patterns
+/// pay for themselves.
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            singles: true,
+            stages: Stages::none(),
+            band_patterns: true,
+            branching: Branching::default(),
+        }
+    }
+}
+
+impl SolverConfig {
+    /// The propagation levels from none up to every deduction, each adding to the one before.
+    pub const LEVELS: [&'static str; 5] = ["none", "singles", "locked", "subsets", "bands"];
+
+    pub fn level(name: &str) -> Option<Self> {
+        let level = Self::LEVELS.iter().position(|level| *level == name)?;
+        Some(SolverConfig {
+            singles: level >= 1,
+            stages: Stages {
+                locked_candidates: level >= 2,
+                naked_subsets: if level >= 3 { 4 } else { 0 },
+                hidden_subsets: if level >= 3 { 4 } else { 0 },
+            },
+            band_patterns: level >= 4,
+            ..SolverConfig::default()
+        })
+    }
+
+    pub fn with_branching(mut self, branching: Branching) -> Self {
+        self.branching = branching;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{solve_with_config, Variant};
+    use super::*;
+
+    #[test]
+    fn every_level_and_branching_solves_alike() {
+        let solved = include_str!("../../../test-data/forum_hardest_1106.solved");
+        let variant = Variant::default();
+        for level in SolverConfig::LEVELS {
+            for name in Branching::NAMES {
+                let branching = Branching::from_name(name).unwrap();
+                let config = SolverConfig::level(level)
+                    .unwrap()
+                    .with_branching(branching);
+                for line in solved.lines().step_by(53) {
+                    let (puzzle, solution) = line.split_once(',').unwrap();
+                    let found = solve_with_config(puzzle, &variant, &config);
+                    assert_eq!(found.as_deref(), Some(solution), "{} {}", level, name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1383 @@
+/// A variable of a CNF encoding: a cell holding a digit, counted from 0, or one of the
+/// auxiliary variables of the constraint's own encoding, numbered from 0.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Var {
+    Digit(usize, usize),
+    Aux(usize),
+}
+
+/// One CNF clause: at least one of its variables takes the given value.
+pub type Clause = Vec<(Var, bool)>;
+
+/// A variant rule narrowing per-cell candidate masks (bit `d` stands for digit `d + 1`).
+/// `propagate` returns `false` once the candidates can no longer satisfy the rule.
+pub trait Constraint: Send + Sync {
+    fn propagate(&self, candidates: &mut [u32]) -> bool;
+
+    /// The rule as clauses over digits `0..size`, for SAT export, or `None` when it has no
+    /// encoding here.
+    fn clauses(&self, _size: usize) -> Option<Vec<Clause>> {
+        None
+    }
+
+    /// The rule's type, for messages.
+    fn name(&self) -> String {
+        let name = std::any::type_name::<Self>();
+        // Drop the module paths, keeping generic arguments: `Line<Renban>`.
+        let mut short = String::new();
+        for part in name.split_inclusive(['<', '>', ',', ' ']) {
+            short.push_str(part.rsplit("::").next().unwrap_or(part));
+        }
+        short
+    }
+}
+
+/// Cell `a` holding `first` and cell `b` holding `second` cannot both happen.
+fn forbid(a: usize, first: usize, b: usize, second: usize) -> Clause {
+    vec![
+        (Var::Digit(a, first), false),
+        (Var::Digit(b, second), false),
+    ]
+}
+
+/// Every digit pair of the two cells failing `allowed`, forbidden.
+fn forbid_unless(
+    a: usize,
+    b: usize,
+    size: usize,
+    allowed: impl Fn(usize, usize) -> bool,
+) -> Vec<Clause> {
+    let mut clauses = vec![];
+    for first in 0..size {
+        for second in 0..size {
+            if !allowed(first, second) {
+                clauses.push(forbid(a, first, b, second));
+            }
+        }
+    }
+    clauses
+}
+
+/// The values one term of a sum can take: exactly one alternative holds, the one whose
+/// literals all do.
+type Term = Vec<(Clause, usize)>;
+
+/// A cell's digit as a term of a sum.
+fn digit_term(cell: usize, size: usize) -> Term {
+    (0..size)
+        .map(|digit| (vec![(Var::Digit(cell, digit), true)], digit + 1))
+        .collect()
+}
+
+/// Clauses using auxiliary variables, numbered in the order they are made.
+#[derive(Default)]
+struct Encoding {
+    clauses: Vec<Clause>,
+    auxiliaries: usize,
+}
+
+impl Encoding {
+    fn auxiliary(&mut self) -> Var {
+        self.auxiliaries += 1;
+        Var::Aux(self.auxiliaries - 1)
+    }
+
+    /// Running sums of the terms, one auxiliary per reachable partial sum: the variable at
+    /// index `s` of the result holds exactly when the terms add up to `s`. Terms are never
+    /// negative, so partial sums above `limit` are ruled out.
+    fn sum(&mut self, terms: &[Term], limit: usize) -> Vec<Option<Var>> {
+        let zero = self.auxiliary();
+        self.clauses.push(vec![(zero, true)]);
+        let mut sums = vec![Some(zero)];
+        for term in terms.iter() {
+            let mut next = vec![None; limit + 1];
+            for (sum, _) in sums.iter().enumerate().filter(|(_, var)| var.is_some()) {
+                for (_, value) in term.iter().filter(|(_, value)| sum + value <= limit) {
+                    next[sum + value] = Some(zero);
+                }
+            }
+            for var in next.iter_mut().filter(|var| var.is_some()) {
+                *var = Some(self.auxiliary());
+            }
+            for (literals, value) in term.iter() {
+                let unless = literals.iter().map(|(var, holds)| (*var, !holds));
+                for (sum, before) in sums.iter().enumerate() {
+                    let Some(before) = before else { continue };
+                    let mut clause = vec![(*before, false)];
+                    clause.extend(unless.clone());
+                    clause.extend(
+                        next.get(sum + value)
+                            .copied()
+                            .flatten()
+                            .map(|var| (var, true)),
+                    );
+                    self.clauses.push(clause);
+                }
+                for (sum, after) in next.iter().enumerate() {
+                    let Some(after) = after else { continue };
+                    let mut clause = vec![(*after, false)];
+                    clause.extend(unless.clone());
+                    let before = sum.checked_sub(*value).and_then(|sum| sums.get(sum));
+                    clause.extend(before.copied().flatten().map(|var| (var, true)));
+                    self.clauses.push(clause);
+                }
+            }
+            sums = next;
+        }
+        sums
+    }
+
+    /// Requires the running sums to reach `total`.
+    fn require(&mut self, sums: &[Option<Var>], total: usize) {
+        let var = sums.get(total).copied().flatten();
+        self.clauses
+            .push(var.map(|var| (var, true)).into_iter().collect());
+    }
+}
+
+const MAX_DIGITS: usize = 32;
+
+fn orthogonal_pairs(size: usize) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    for row in 0..size {
+        for col in 0..size {
+            let cell = row * size + col;
+            if col + 1 < size {
+                pairs.push((cell, cell + 1));
+            }
+            if row + 1 < size {
+                pairs.push((cell, cell + size));
+            }
+        }
+    }
+    pairs
+}
+
+fn is_consecutive(a: usize, b: usize) -> bool {
+    a + 1 == b || b + 1 == a
+}
+
+fn is_ratio_2(a: usize, b: usize) -> bool {
+    a * 2 == b || b * 2 == a
+}
+
+struct Relation {
+    allowed: [u32; MAX_DIGITS],
+}
+
+impl Relation {
+    fn new(relation: fn(usize, usize) -> bool) -> Self {
+        let mut allowed = [0; MAX_DIGITS];
+        for (digit, mask) in allowed.iter_mut().enumerate() {
+            for other in 0..MAX_DIGITS {
+                if relation(digit + 1, other + 1) {
+                    *mask |= 1 << other;
+                }
+            }
+        }
+        Relation { allowed }
+    }
+
+    fn supported(&self, candidates: u32, others: u32) -> u32 {
+        let mut supported = 0;
+        let mut bits = candidates;
+        while bits != 0 {
+            let digit = bits.trailing_zeros() as usize;
+            if self.allowed[digit] & others != 0 {
+                supported |= 1 << digit;
+            }
+            bits &= bits - 1;
+        }
+        supported
+    }
+
+    fn propagate<'a>(
+        &self,
+        pairs: impl Iterator<Item = (&'a usize, &'a usize)>,
+        candidates: &mut [u32],
+    ) -> bool {
+        for (a, b) in pairs {
+            candidates[*a] = self.supported(candidates[*a], candidates[*b]);
+            candidates[*b] = self.supported(candidates[*b], candidates[*a]);
+            if candidates[*a] == 0 || candidates[*b] == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn clauses<'a>(
+        &self,
+        pairs: impl Iterator<Item = (&'a usize, &'a usize)>,
+        size: usize,
+    ) -> Vec<Clause> {
+        pairs
+            .flat_map(|(a, b)| {
+                forbid_unless(*a, *b, size, |first, second| {
+                    self.allowed[first] & 1 << second != 0
+                })
+            })
+            .collect()
+    }
+}
+
+lazy_static! {
+    static ref WHISPERS: Relation = Relation::new(|a, b| a + 5 <= b || b + 5 <= a);
+}
+
+/// Cell pairs whose digits must satisfy a symmetric relation.
+pub struct Pairs {
+    pairs: Vec<(usize, usize)>,
+    relation: Relation,
+}
+
+impl Pairs {
+    pub fn new(pairs: Vec<(usize, usize)>, relation: fn(usize, usize) -> bool) -> Self {
+        Pairs {
+            pairs,
+            relation: Relation::new(relation),
+        }
+    }
+}
+
+impl Constraint for Pairs {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        self.relation
+            .propagate(self.pairs.iter().map(|(a, b)| (a, b)), candidates)
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        Some(
+            self.relation
+                .clauses(self.pairs.iter().map(|(a, b)| (a, b)), size),
+        )
+    }
+}
+
+/// No two orthogonally adjacent cells of a `size` x `size` grid contain consecutive digits.
+pub fn non_consecutive(size: usize) -> Pairs {
+    Pairs::new(orthogonal_pairs(size), |a, b| !is_consecutive(a, b))
+}
+
+/// Kropki dots: white pairs are consecutive, black pairs are in a 1:2 ratio.
+/// With `negative`, every other orthogonal pair of the `size` x `size` grid is neither.
+pub struct Kropki {
+    white: Pairs,
+    black: Pairs,
+    others: Pairs,
+}
+
+impl Kropki {
+    pub fn new(
+        size: usize,
+        white: Vec<(usize, usize)>,
+        black: Vec<(usize, usize)>,
+        negative: bool,
+    ) -> Self {
+        let others = if negative {
+            orthogonal_pairs(size)
+                .into_iter()
+                .filter(|pair| !Self::contains(&white, *pair) && !Self::contains(&black, *pair))
+                .collect()
+        } else {
+            vec![]
+        };
+        Kropki {
+            white: Pairs::new(white, is_consecutive),
+            black: Pairs::new(black, is_ratio_2),
+            others: Pairs::new(others, |a, b| !is_consecutive(a, b) && !is_ratio_2(a, b)),
+        }
+    }
+
+    fn contains(pairs: &[(usize, usize)], (a, b): (usize, usize)) -> bool {
+        pairs
+            .iter()
+            .any(|&(x, y)| (x == a && y == b) || (x == b && y == a))
+    }
+}
+
+impl Constraint for Kropki {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        self.white.propagate(candidates)
+            && self.black.propagate(candidates)
+            && self.others.propagate(candidates)
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        let mut clauses = self.white.clauses(size)?;
+        clauses.extend(self.black.clauses(size)?);
+        clauses.extend(self.others.clauses(size)?);
+        Some(clauses)
+    }
+}
+
+/// Digits strictly increase from the bulb (first cell) to the tip.
+pub struct Thermo {
+    cells: Vec<usize>,
+}
+
+impl Thermo {
+    pub fn new(cells: Vec<usize>) -> Self {
+        Thermo { cells }
+    }
+}
+
+fn raise_above(candidates: &mut [u32], lower: usize, higher: usize) -> bool {
+    let lowest = candidates[lower].trailing_zeros();
+    if lowest >= 31 {
+        return false;
+    }
+    candidates[higher] &= !((2 << lowest) - 1);
+    candidates[higher] != 0
+}
+
+fn lower_below(candidates: &mut [u32], lower: usize, higher: usize) -> bool {
+    let upper = candidates[higher];
+    if upper == 0 {
+        return false;
+    }
+    candidates[lower] &= (1 << (31 - upper.leading_zeros())) - 1;
+    candidates[lower] != 0
+}
+
+impl Constraint for Thermo {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        self.cells
+            .windows(2)
+            .all(|pair| raise_above(candidates, pair[0], pair[1]))
+            && self
+                .cells
+                .windows(2)
+                .rev()
+                .all(|pair| lower_below(candidates, pair[0], pair[1]))
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        Some(
+            self.cells
+                .windows(2)
+                .flat_map(|pair| {
+                    forbid_unless(pair[0], pair[1], size, |lower, higher| lower < higher)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Inequality signs between adjacent cells, given as `(greater, smaller)` pairs.
+pub struct GreaterThan {
+    pairs: Vec<(usize, usize)>,
+}
+
+impl GreaterThan {
+    pub fn new(pairs: Vec<(usize, usize)>) -> Self {
+        GreaterThan { pairs }
+    }
+}
+
+impl Constraint for GreaterThan {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        self.pairs.iter().all(|&(greater, smaller)| {
+            raise_above(candidates, smaller, greater) && lower_below(candidates, smaller, greater)
+        })
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        Some(
+            self.pairs
+                .iter()
+                .flat_map(|&(greater, smaller)| {
+                    forbid_unless(greater, smaller, size, |greater, smaller| greater > smaller)
+                })
+                .collect(),
+        )
+    }
+}
+
+fn add_digits(sums: u128, digits: u32) -> Option<u128> {
+    let mut next = 0;
+    let mut bits = digits;
+    while bits != 0 {
+        let value = bits.trailing_zeros() + 1;
+        if value > sums.leading_zeros() {
+            return None;
+        }
+        next |= sums << value;
+        bits &= bits - 1;
+    }
+    Some(next)
+}
+
+fn reachable_sums<'a>(digits: impl Iterator<Item = &'a u32>) -> Option<u128> {
+    let mut sums = 1;
+    for digits in digits {
+        sums = add_digits(sums, *digits)?;
+    }
+    Some(sums)
+}
+
+/// Keeps only digits that can take part in a sum of `cells` contained in `targets`
+/// (bit `s` stands for sum `s`). Sums beyond 127 are not tracked and leave the cells as they are.
+fn restrict_sum(cells: &[usize], candidates: &mut [u32], targets: u128) -> bool {
+    if cells.is_empty() {
+        return targets & 1 != 0;
+    }
+    for (index, cell) in cells.iter().enumerate() {
+        let others = cells
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, other)| &candidates[*other]);
+        let others = match reachable_sums(others) {
+            Some(others) => others,
+            None => return true,
+        };
+        let mut kept = 0;
+        let mut bits = candidates[*cell];
+        while bits != 0 {
+            let digit = bits.trailing_zeros();
+            if (others << (digit + 1)) & targets != 0 {
+                kept |= 1 << digit;
+            }
+            bits &= bits - 1;
+        }
+        candidates[*cell] = kept;
+        if kept == 0 {
+            return false;
+        }
+    }
+    true
+}
+
+fn distinct(cells: &[usize], candidates: &mut [u32]) -> bool {
+    for cell in cells.iter() {
+        let digit = candidates[*cell];
+        if digit.count_ones() == 1 {
+            for other in cells.iter().filter(|other| *other != cell) {
+                candidates[*other] &= !digit;
+                if candidates[*other] == 0 {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// A rule applied to the ordered cells of a line.
+pub trait LineRule: Send + Sync {
+    fn propagate(&self, cells: &[usize], candidates: &mut [u32]) -> bool;
+
+    fn clauses(&self, _cells: &[usize], _size: usize) -> Option<Vec<Clause>> {
+        None
+    }
+}
+
+pub struct Line<R: LineRule> {
+    cells: Vec<usize>,
+    rule: R,
+}
+
+impl<R: LineRule> Line<R> {
+    pub fn new(cells: Vec<usize>, rule: R) -> Self {
+        Line { cells, rule }
+    }
+}
+
+impl<R: LineRule> Constraint for Line<R> {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        self.rule.propagate(&self.cells, candidates)
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        self.rule.clauses(&self.cells, size)
+    }
+}
+
+/// Adjacent digits on the line differ by at least 5.
+pub struct GermanWhispers;
+
+impl LineRule for GermanWhispers {
+    fn propagate(&self, cells: &[usize], candidates: &mut [u32]) -> bool {
+        WHISPERS.propagate(cells.iter().zip(cells.iter().skip(1)), candidates)
+    }
+
+    fn clauses(&self, cells: &[usize], size: usize) -> Option<Vec<Clause>> {
+        Some(WHISPERS.clauses(cells.iter().zip(cells.iter().skip(1)), size))
+    }
+}
+
+/// The line holds a set of consecutive digits in any order.
+pub struct Renban;
+
+impl LineRule for Renban {
+    fn propagate(&self, cells: &[usize], candidates: &mut [u32]) -> bool {
+        let length = cells.len() as u32;
+        if length == 0 || length > MAX_DIGITS as u32 {
+            return length == 0;
+        }
+        let all = cells.iter().fold(0, |all, cell| all | candidates[*cell]);
+        let mut windows = 0;
+        for start in 0..=MAX_DIGITS as u32 - length {
+            let window = (u32::MAX >> (MAX_DIGITS as u32 - length)) << start;
+            if window & !all == 0 && cells.iter().all(|cell| candidates[*cell] & window != 0) {
+                windows |= window;
+            }
+        }
+        for cell in cells.iter() {
+            candidates[*cell] &= windows;
+            if candidates[*cell] == 0 {
+                return false;
+            }
+        }
+        distinct(cells, candidates)
+    }
+
+    /// Distinct digits less than the line's length apart are consecutive.
+    fn clauses(&self, cells: &[usize], size: usize) -> Option<Vec<Clause>> {
+        let mut clauses = vec![];
+        for (index, a) in cells.iter().enumerate() {
+            for b in cells.iter().skip(index + 1) {
+                clauses.extend(forbid_unless(*a, *b, size, |first, second| {
+                    first != second && first.abs_diff(second) < cells.len()
+                }));
+            }
+        }
+        Some(clauses)
+    }
+}
+
+/// The line reads the same in both directions.
+pub struct Palindrome;
+
+impl LineRule for Palindrome {
+    fn propagate(&self, cells: &[usize], candidates: &mut [u32]) -> bool {
+        for (a, b) in cells.iter().zip(cells.iter().rev()) {
+            let both = candidates[*a] & candidates[*b];
+            candidates[*a] = both;
+            candidates[*b] = both;
+            if both == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn clauses(&self, cells: &[usize], size: usize) -> Option<Vec<Clause>> {
+        Some(
+            cells
+                .iter()
+                .zip(cells.iter().rev())
+                .flat_map(|(a, b)| forbid_unless(*a, *b, size, |first, second| first == second))
+                .collect(),
+        )
+    }
+}
+
+/// Region borders split the line into segments which all have the same sum.
+pub struct RegionSum {
+    regions: Vec<usize>,
+}
+
+impl Default for RegionSum {
+    fn default() -> Self {
+        RegionSum::new(
+            (0..81)
+                .map(|cell| (cell / 27) * 3 + (cell % 9) / 3)
+                .collect(),
+        )
+    }
+}
+
+impl RegionSum {
+    /// `regions` holds the region index of every cell; the default uses the 9x9 boxes.
+    pub fn new(regions: Vec<usize>) -> Self {
+        RegionSum { regions }
+    }
+
+    fn segments<'a>(&self, cells: &'a [usize]) -> Vec<&'a [usize]> {
+        let mut segments = vec![];
+        let mut start = 0;
+        for index in 1..=cells.len() {
+            if index == cells.len() || self.regions[cells[index]] != self.regions[cells[start]] {
+                segments.push(&cells[start..index]);
+                start = index;
+            }
+        }
+        segments
+    }
+}
+
+impl LineRule for RegionSum {
+    fn propagate(&self, cells: &[usize], candidates: &mut [u32]) -> bool {
+        let segments = self.segments(cells);
+        let mut targets = u128::MAX;
+        for segment in segments.iter() {
+            match reachable_sums(segment.iter().map(|cell| &candidates[*cell])) {
+                Some(sums) => targets &= sums,
+                None => return true,
+            }
+        }
+        targets != 0
+            && segments
+                .iter()
+                .all(|segment| restrict_sum(segment, candidates, targets))
+    }
+
+    fn clauses(&self, cells: &[usize], size: usize) -> Option<Vec<Clause>> {
+        let mut encoding = Encoding::default();
+        let sums = self
+            .segments(cells)
+            .iter()
+            .map(|segment| {
+                let terms = segment.iter().map(|cell| digit_term(*cell, size));
+                encoding.sum(&terms.collect::<Vec<Term>>(), segment.len() * size)
+            })
+            .collect::<Vec<Vec<Option<Var>>>>();
+        for pair in sums.windows(2) {
+            for (sum, var) in pair[0].iter().enumerate() {
+                if let Some(var) = var {
+                    let mut clause = vec![(*var, false)];
+                    clause.extend(pair[1].get(sum).copied().flatten().map(|var| (var, true)));
+                    encoding.clauses.push(clause);
+                }
+            }
+        }
+        Some(encoding.clauses)
+    }
+}
+
+/// The circle cell equals the sum of the arrow cells.
+pub struct Arrow {
+    circle: usize,
+    arrow: Vec<usize>,
+}
+
+impl Arrow {
+    pub fn new(circle: usize, arrow: Vec<usize>) -> Self {
+        Arrow { circle, arrow }
+    }
+}
+
+impl Constraint for Arrow {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        let sums = match reachable_sums(self.arrow.iter().map(|cell| &candidates[*cell])) {
+            Some(sums) => sums,
+            None => return true,
+        };
+        candidates[self.circle] &= (sums >> 1) as u32;
+        candidates[self.circle] != 0
+            && restrict_sum(
+                &self.arrow,
+                candidates,
+                (candidates[self.circle] as u128) << 1,
+            )
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        let mut encoding = Encoding::default();
+        let terms = self.arrow.iter().map(|cell| digit_term(*cell, size));
+        let sums = encoding.sum(&terms.collect::<Vec<Term>>(), size);
+        for (sum, var) in sums.iter().enumerate() {
+            if let Some(var) = var {
+                let mut clause = vec![(*var, false)];
+                if sum > 0 {
+                    clause.push((Var::Digit(self.circle, sum - 1), true));
+                }
+                encoding.clauses.push(clause);
+            }
+        }
+        Some(encoding.clauses)
+    }
+}
+
+/// The digits between the lowest and the highest digit of a row or column sum to `total`.
+pub struct Sandwich {
+    cells: Vec<usize>,
+    total: u32,
+}
+
+impl Sandwich {
+    /// A row or column given in order; its length is the highest digit.
+    pub fn new(cells: Vec<usize>, total: u32) -> Self {
+        Sandwich { cells, total }
+    }
+
+    pub fn row(row: usize, total: u32) -> Self {
+        Sandwich {
+            cells: (row * 9..row * 9 + 9).collect(),
+            total,
+        }
+    }
+
+    pub fn column(column: usize, total: u32) -> Self {
+        Sandwich {
+            cells: (column..81).step_by(9).collect(),
+            total,
+        }
+    }
+
+    /// Parses the clues outside the grid: nine column totals read along the top,
+    /// then nine row totals read down the left, comma separated, blank where there is no clue.
+    pub fn parse(clues: &str) -> Result<Vec<Sandwich>, String> {
+        let clues = clues.split(',').map(str::trim).collect::<Vec<&str>>();
+        if clues.len() != 18 {
+            return Err(format!("expected 18 sandwich clues, found {}", clues.len()));
+        }
+        let mut sandwiches = vec![];
+        for (index, clue) in clues.iter().enumerate() {
+            if clue.is_empty() {
+                continue;
+            }
+            let total = clue
+                .parse::<u32>()
+                .map_err(|_| format!("invalid sandwich clue {:?}", clue))?;
+            sandwiches.push(if index < 9 {
+                Sandwich::column(index, total)
+            } else {
+                Sandwich::row(index - 9, total)
+            });
+        }
+        Ok(sandwiches)
+    }
+}
+
+impl Constraint for Sandwich {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        let lowest = 1;
+        let highest = 1 << (self.cells.len() - 1);
+        let line = self
+            .cells
+            .iter()
+            .map(|cell| candidates[*cell])
+            .collect::<Vec<u32>>();
+        let mut supported = vec![0; line.len()];
+        for (low, high) in (0..line.len())
+            .flat_map(|low| (0..line.len()).map(move |high| (low, high)))
+            .filter(|&(low, high)| {
+                low != high && line[low] & lowest != 0 && line[high] & highest != 0
+            })
+        {
+            let mut attempt = line
+                .iter()
+                .map(|digits| digits & !(lowest | highest))
+                .collect::<Vec<u32>>();
+            attempt[low] = lowest;
+            attempt[high] = highest;
+            if attempt.contains(&0) {
+                continue;
+            }
+            let between = (low.min(high) + 1..low.max(high)).collect::<Vec<usize>>();
+            let (count, size) = (between.len() as u32, line.len() as u32);
+            if (count + 3) * count / 2 > self.total
+                || (2 * size - count - 1) * count / 2 < self.total
+            {
+                continue;
+            }
+            if !restrict_sum(
+                &between,
+                &mut attempt,
+                1u128.checked_shl(self.total).unwrap_or(0),
+            ) {
+                continue;
+            }
+            for (supported, digits) in supported.iter_mut().zip(attempt.iter()) {
+                *supported |= digits;
+            }
+        }
+        for (cell, supported) in self.cells.iter().zip(supported.iter()) {
+            candidates[*cell] &= supported;
+            if candidates[*cell] == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Parity auxiliaries count the crusts, the lowest and highest digits, met before each
+    /// cell; the cells met after exactly one add up to the total.
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        let highest = self.cells.len() - 1;
+        if highest >= size {
+            return Some(vec![vec![]]);
+        }
+        let mut encoding = Encoding::default();
+        let mut odd = encoding.auxiliary();
+        encoding.clauses.push(vec![(odd, false)]);
+        let mut terms = vec![];
+        for cell in self.cells.iter() {
+            let (lowest, highest) = (Var::Digit(*cell, 0), Var::Digit(*cell, highest));
+            let (inside, after) = (encoding.auxiliary(), encoding.auxiliary());
+            encoding.clauses.extend([
+                // `inside` holds when an odd number of crusts came before and this is none.
+                vec![(inside, false), (odd, true)],
+                vec![(inside, false), (lowest, false)],
+                vec![(inside, false), (highest, false)],
+                vec![
+                    (inside, true),
+                    (odd, false),
+                    (lowest, true),
+                    (highest, true),
+                ],
+                // `after` flips `odd` on a crust.
+                vec![(after, true), (odd, false), (lowest, true), (highest, true)],
+                vec![(after, false), (odd, true), (lowest, true), (highest, true)],
+                vec![(after, true), (odd, true), (lowest, false)],
+                vec![(after, true), (odd, true), (highest, false)],
+                vec![(after, false), (odd, false), (lowest, false)],
+                vec![(after, false), (odd, false), (highest, false)],
+            ]);
+            let mut term = vec![(vec![(inside, false)], 0)];
+            term.extend((0..size).map(|digit| {
+                let literals = vec![(inside, true), (Var::Digit(*cell, digit), true)];
+                (literals, digit + 1)
+            }));
+            terms.push(term);
+            odd = after;
+        }
+        let total = self.total as usize;
+        let sums = encoding.sum(&terms, total);
+        encoding.require(&sums, total);
+        Some(encoding.clauses)
+    }
+}
+
+/// The digits that can join the `required` ones in `count` distinct digits adding up to `sum`,
+/// the others coming from `allowed`. Sums beyond 127 are not tracked and keep every digit.
+fn distinct_sum_digits(count: usize, sum: u32, allowed: u32, required: u32) -> u32 {
+    if sum > 127 {
+        return allowed | required;
+    }
+    let digits = 32 - (allowed | required).leading_zeros() as usize;
+    // `sums[k]` has bit `s` set when `k` of the digits seen so far can add up to `s`.
+    let step = |sums: &Vec<u128>, digit: usize| {
+        let value = digit as u32 + 1;
+        let mut next = vec![0u128; count + 1];
+        for k in 0..=count {
+            if required & (1 << digit) == 0 {
+                next[k] |= sums[k];
+            }
+            if k > 0 && (allowed | required) & (1 << digit) != 0 {
+                next[k] |= sums[k - 1] << value;
+            }
+        }
+        next
+    };
+    let mut empty = vec![0u128; count + 1];
+    empty[0] = 1;
+    // `below[d]` covers the digits under `d`, `above[d]` those over it.
+    let mut below = vec![empty.clone()];
+    for digit in 0..digits {
+        below.push(step(&below[digit], digit));
+    }
+    let mut above = vec![empty; digits];
+    for digit in (1..digits).rev() {
+        above[digit - 1] = step(&above[digit], digit);
+    }
+    let mut kept = 0;
+    for digit in (0..digits).filter(|digit| (allowed | required) & (1 << digit) != 0) {
+        let Some(rest) = sum.checked_sub(digit as u32 + 1) else {
+            continue;
+        };
+        let fits = (0..count).any(|k| {
+            (0..=rest).any(|part| {
+                below[digit][k] >> part & 1 != 0
+                    && above[digit][count - 1 - k] >> (rest - part) & 1 != 0
+            })
+        });
+        if fits {
+            kept |= 1 << digit;
+        }
+    }
+    kept
+}
+
+/// A killer cage: distinct digits, summing to `sum` when it is given.
+pub struct Cage {
+    cells: Vec<usize>,
+    sum: Option<u32>,
+}
+
+impl Cage {
+    pub fn new(cells: Vec<usize>, sum: Option<u32>) -> Self {
+        Cage { cells, sum }
+    }
+}
+
+impl Constraint for Cage {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        if !distinct(&self.cells, candidates) {
+            return false;
+        }
+        let Some(sum) = self.sum else {
+            return true;
+        };
+        let allowed = self
+            .cells
+            .iter()
+            .fold(0, |digits, cell| digits | candidates[*cell]);
+        let required = self
+            .cells
+            .iter()
+            .map(|cell| candidates[*cell])
+            .filter(|digits| digits.count_ones() == 1)
+            .fold(0, |digits, digit| digits | digit);
+        let kept = distinct_sum_digits(self.cells.len(), sum, allowed, required);
+        if required & !kept != 0 {
+            return false;
+        }
+        for cell in self.cells.iter() {
+            candidates[*cell] &= kept;
+        }
+        restrict_sum(&self.cells, candidates, 1u128.checked_shl(sum).unwrap_or(0))
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        let mut encoding = Encoding::default();
+        for (index, a) in self.cells.iter().enumerate() {
+            for b in self.cells.iter().skip(index + 1) {
+                let clauses = forbid_unless(*a, *b, size, |first, second| first != second);
+                encoding.clauses.extend(clauses);
+            }
+        }
+        if let Some(sum) = self.sum {
+            let terms = self.cells.iter().map(|cell| digit_term(*cell, size));
+            let sums = encoding.sum(&terms.collect::<Vec<Term>>(), sum as usize);
+            encoding.require(&sums, sum as usize);
+        }
+        Some(encoding.clauses)
+    }
+}
+
+/// Cells limited to a fixed set of digits, such as parity shading.
+pub struct Allowed {
+    cells: Vec<usize>,
+    digits: u32,
+}
+
+impl Allowed {
+    pub fn new(cells: Vec<usize>, digits: u32) -> Self {
+        Allowed { cells, digits }
+    }
+}
+
+impl Constraint for Allowed {
+    fn propagate(&self, candidates: &mut [u32]) -> bool {
+        self.cells.iter().all(|cell| {
+            candidates[*cell] &= self.digits;
+            candidates[*cell] != 0
+        })
+    }
+
+    fn clauses(&self, size: usize) -> Option<Vec<Clause>> {
+        Some(
+            self.cells
+                .iter()
+                .flat_map(|cell| {
+                    (0..size)
+                        .filter(|digit| self.digits & 1 << digit == 0)
+                        .map(|digit| vec![(Var::Digit(*cell, digit), false)])
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::variant::Parity;
+    use super::super::{count_in, solve_with, Geometry, Model, Variant};
+    use super::*;
+
+    /// A classic puzzle with a single solution, which the impossible clues below break.
+    const PUZZLE: &str =
+        "000000008003000400090020060000079000000061200060502070008000500010000020405000003";
+    const SOLUTION: &str =
+        "621943758783615492594728361142879635357461289869532174238197546916354827475286913";
+
+    fn boxed(constraint: impl Constraint + 'static) -> Vec<Box<dyn Constraint>> {
+        vec![Box::new(constraint)]
+    }
+
+    /// The solutions of `puzzle` from the bitboard engine, with `variant`, and from the wide
+    /// engine, with `constraints`.
+    fn solutions(
+        puzzle: &str,
+        variant: &Variant,
+        constraints: Vec<Box<dyn Constraint>>,
+    ) -> [Option<String>; 2] {
+        let mut model = Model::new(Geometry::classic());
+        model.givens = model.geometry.parse(puzzle).unwrap();
+        model.constraints = constraints;
+        [solve_with(puzzle, variant), model.solve()]
+    }
+
+    fn solve_both(
+        puzzle: &str,
+        rules: impl Fn() -> Vec<Box<dyn Constraint>>,
+    ) -> [Option<String>; 2] {
+        let mut variant = Variant::new();
+        variant.constraints = rules();
+        solutions(puzzle, &variant, rules())
+    }
+
+    /// Checks that `puzzle` has several solutions without the rules and that both engines
+    /// find its single solution with them.
+    fn assert_solves(puzzle: &str, solution: &str, rules: impl Fn() -> Vec<Box<dyn Constraint>>) {
+        assert_eq!(count_in(puzzle, &Geometry::classic(), 2), Some(2));
+        let solution = Some(solution.to_string());
+        assert_eq!(solve_both(puzzle, rules), [solution.clone(), solution]);
+    }
+
+    /// Checks that neither engine solves `PUZZLE` under a rule its solution breaks.
+    fn assert_rejects(rules: impl Fn() -> Vec<Box<dyn Constraint>>) {
+        assert_eq!(solve_both(PUZZLE, rules), [None, None]);
+    }
+
+    /// The orthogonal pairs of `SOLUTION` holding consecutive digits, then those in a 1:2 ratio.
+    fn dots() -> [Vec<(usize, usize)>; 2] {
+        let digit = |cell: usize| SOLUTION.as_bytes()[cell] - b'0';
+        let (mut white, mut black) = (vec![], vec![]);
+        for (a, b) in orthogonal_pairs(9) {
+            if is_consecutive(digit(a).into(), digit(b).into()) {
+                white.push((a, b));
+            } else if is_ratio_2(digit(a).into(), digit(b).into()) {
+                black.push((a, b));
+            }
+        }
+        [white, black]
+    }
+
+    #[test]
+    fn solves_non_consecutive_puzzles() {
+        assert_solves(
+            "000009060000003000000800000040000009000000803000000200000090000004000000900000050",
+            "135279468468513792792846135246381579579624813813957246351792684684135927927468351",
+            || boxed(non_consecutive(9)),
+        );
+        assert_rejects(|| boxed(non_consecutive(9)));
+    }
+
+    #[test]
+    fn solves_kropki_puzzles() {
+        let [white, black] = dots();
+        assert_solves(&"0".repeat(81), SOLUTION, || {
+            boxed(Kropki::new(9, white.clone(), black.clone(), true))
+        });
+        let puzzle = format!("{}1{}", "0".repeat(26), "0".repeat(54));
+        assert_solves(&puzzle, SOLUTION, || {
+            boxed(Kropki::new(9, white.clone(), black.clone(), false))
+        });
+        assert_rejects(|| boxed(Kropki::new(9, vec![(0, 1)], vec![], false)));
+        assert_rejects(|| boxed(Kropki::new(9, vec![], vec![(0, 1)], false)));
+        assert_rejects(|| boxed(Kropki::new(9, vec![], vec![], true)));
+    }
+
+    /// Whether `clauses` hold for cells holding `values` (counted from 1), the auxiliaries
+    /// being settled by unit propagation, which the encodings must allow.
+    fn accepts(clauses: &[Clause], values: &[usize]) -> bool {
+        let count = clauses
+            .iter()
+            .flatten()
+            .filter_map(|(var, _)| match var {
+                Var::Aux(index) => Some(index + 1),
+                Var::Digit(..) => None,
+            })
+            .max();
+        let mut auxiliaries = vec![None; count.unwrap_or(0)];
+        loop {
+            let mut changed = false;
+            for clause in clauses.iter() {
+                let value = |var: &Var| match *var {
+                    Var::Digit(cell, digit) => Some(values[cell] == digit + 1),
+                    Var::Aux(index) => auxiliaries[index],
+                };
+                if clause.iter().any(|(var, holds)| value(var) == Some(*holds)) {
+                    continue;
+                }
+                let open = clause
+                    .iter()
+                    .filter(|(var, _)| value(var).is_none())
+                    .collect::<Vec<_>>();
+                match open[..] {
+                    [] => return false,
+                    [(Var::Aux(index), holds)] => {
+                        auxiliaries[*index] = Some(*holds);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                assert!(auxiliaries.iter().all(Option::is_some), "{:?}", values);
+                return true;
+            }
+        }
+    }
+
+    /// Every assignment of `1..=size` to `cells` cells.
+    fn assignments(cells: usize, size: usize) -> impl Iterator<Item = Vec<usize>> {
+        (0..size.pow(cells as u32)).map(move |index| {
+            (0..cells)
+                .map(|cell| index / size.pow(cell as u32) % size + 1)
+                .collect()
+        })
+    }
+
+    fn is_distinct(values: &[usize]) -> bool {
+        values
+            .iter()
+            .enumerate()
+            .all(|(index, value)| !values[..index].contains(value))
+    }
+
+    #[test]
+    fn narrows_thermo_bounds() {
+        let thermo = Thermo::new(vec![0, 1, 2]);
+        let mut candidates = [0x1ff; 3];
+        assert!(thermo.propagate(&mut candidates));
+        assert_eq!(candidates, [0b001111111, 0b011111110, 0b111111100]);
+        let mut candidates = [0x1ff, 0x1ff, 1 << 4];
+        assert!(thermo.propagate(&mut candidates));
+        assert_eq!(candidates, [0b111, 0b1110, 1 << 4]);
+        let mut candidates = [0x1ff, 0x1ff, 0b11];
+        assert!(!thermo.propagate(&mut candidates));
+        assert!(!Thermo::new((0..10).collect()).propagate(&mut [0x1ff; 10]));
+    }
+
+    #[test]
+    fn solves_thermo_puzzles() {
+        assert_solves(
+            "000000058000605000000700301040000005007000209009030000008090040006300000400006010",
+            SOLUTION,
+            || boxed(Thermo::new(vec![1, 0, 9, 10, 19])),
+        );
+        assert_rejects(|| boxed(Thermo::new(vec![0, 1])));
+    }
+
+    #[test]
+    fn solves_line_puzzles() {
+        assert_solves(
+            "000000058000605000090700301040000005007000209009030100008090040000300000400006010",
+            SOLUTION,
+            || boxed(Line::new(vec![36, 45, 54, 63, 64], GermanWhispers)),
+        );
+        assert_solves(
+            "000000008000605000090700301040000005007000209009030100008090040000300000400006010",
+            SOLUTION,
+            || boxed(Line::new(vec![0, 9, 10, 19], Renban)),
+        );
+        assert_solves(
+            "000000058000605000090000301040000005007000209009000100008090040006300000400206010",
+            SOLUTION,
+            || boxed(Line::new(vec![12, 21, 22, 31, 40], Palindrome)),
+        );
+        assert_solves(
+            "000000008000605000090700301040000000007000209000030100008090040006300000400206000",
+            SOLUTION,
+            || {
+                boxed(Line::new(
+                    vec![17, 26, 25, 34, 33, 32],
+                    RegionSum::default(),
+                ))
+            },
+        );
+    }
+
+    #[test]
+    fn rejects_broken_lines() {
+        assert_rejects(|| boxed(Line::new(vec![0, 1], GermanWhispers)));
+        assert_rejects(|| boxed(Line::new(vec![0, 1, 2], Renban)));
+        assert_rejects(|| boxed(Line::new(vec![0, 1], Palindrome)));
+        assert_rejects(|| boxed(Line::new(vec![2, 3], RegionSum::default())));
+    }
+
+    #[test]
+    fn solves_arrow_puzzles() {
+        assert_solves(
+            "000000058000605000090700001040000600007000209009030000008090040000300000400006010",
+            SOLUTION,
+            || boxed(Arrow::new(0, vec![1, 2, 11])),
+        );
+        assert_rejects(|| boxed(Arrow::new(0, vec![1, 2])));
+    }
+
+    #[test]
+    fn solves_sandwich_puzzles() {
+        let sandwiches = || {
+            let clues = "13,18,16,30,18,0,13,30,5,0,9,30,21,10,10,0,0,0";
+            let sandwiches = Sandwich::parse(clues).unwrap().into_iter();
+            sandwiches
+                .map(|sandwich| Box::new(sandwich) as Box<dyn Constraint>)
+                .collect()
+        };
+        assert_solves(
+            "000000000080000000000000000000000600000000009000000000008000040000000000000000000",
+            SOLUTION,
+            sandwiches,
+        );
+        assert_rejects(|| boxed(Sandwich::row(0, 1)));
+        assert_rejects(|| boxed(Sandwich::column(0, 0)));
+    }
+
+    #[test]
+    fn solves_greater_than_puzzles() {
+        let pairs = vec![
+            (0, 1),
+            (1, 2),
+            (10, 9),
+            (10, 11),
+            (19, 18),
+            (19, 20),
+            (9, 0),
+            (10, 1),
+            (11, 2),
+            (9, 18),
+            (19, 10),
+            (20, 11),
+        ];
+        assert_solves(
+            "000003058000005000000700001040000600007000209009030000008090040000300000400006010",
+            SOLUTION,
+            || boxed(GreaterThan::new(pairs.clone())),
+        );
+        assert_rejects(|| boxed(GreaterThan::new(vec![(1, 0)])));
+    }
+
+    /// The solutions of `puzzle` with shaded cells, masked by the bitboard engine's variant
+    /// and held by `Allowed` digits on the wide engine.
+    fn solve_parity(puzzle: &str, even: &[usize], odd: &[usize]) -> [Option<String>; 2] {
+        let mut variant = Variant::new();
+        for cell in even.iter() {
+            variant.add_parity(*cell, Parity::Even);
+        }
+        for cell in odd.iter() {
+            variant.add_parity(*cell, Parity::Odd);
+        }
+        let constraints: Vec<Box<dyn Constraint>> = vec![
+            Box::new(Allowed::new(even.to_vec(), 0b010101010)),
+            Box::new(Allowed::new(odd.to_vec(), 0b101010101)),
+        ];
+        solutions(puzzle, &variant, constraints)
+    }
+
+    #[test]
+    fn solves_parity_puzzles() {
+        let puzzle =
+            "000000000080005000000700301040000005007000209009030100008090040006300000000000000";
+        let even = [0, 1, 4, 8, 39, 40, 42, 43, 72, 75, 76, 77];
+        let odd = [2, 3, 5, 6, 7, 36, 37, 38, 41, 44, 73, 74, 78, 79, 80];
+        assert_eq!(count_in(puzzle, &Geometry::classic(), 2), Some(2));
+        let solution = Some(SOLUTION.to_string());
+        assert_eq!(
+            solve_parity(puzzle, &even, &odd),
+            [solution.clone(), solution]
+        );
+        assert_eq!(solve_parity(PUZZLE, &[2], &[]), [None, None]);
+        assert_eq!(solve_parity(PUZZLE, &[], &[0]), [None, None]);
+    }
+
+    #[test]
+    fn narrows_cages_to_distinct_digits() {
+        let mut candidates = vec![0b111111111; 9];
+        assert!(Cage::new(vec![0, 1], Some(4)).propagate(&mut candidates));
+        assert_eq!(candidates[..2], [0b101, 0b101]);
+        let mut candidates = vec![0b111111111; 9];
+        assert!(Cage::new(vec![0, 1, 2], Some(24)).propagate(&mut candidates));
+        assert_eq!(candidates[..3], [0b111000000; 3]);
+        let mut candidates = vec![0b111111111; 9];
+        candidates[0] = 0b10;
+        assert!(Cage::new(vec![0, 1, 2], Some(7)).propagate(&mut candidates));
+        assert_eq!(candidates[..3], [0b10, 0b1001, 0b1001]);
+        let mut candidates = vec![0b111111111; 9];
+        assert!(!Cage::new(vec![0, 1, 2], Some(5)).propagate(&mut candidates));
+    }
+
+    #[test]
+    fn solves_killer_puzzles() {
+        // A cage over each row of each box, holding the sum of `SOLUTION` there.
+        let cages = || {
+            let digits = SOLUTION.bytes().map(|digit| (digit - b'0') as u32);
+            let digits = digits.collect::<Vec<u32>>();
+            (0..27)
+                .map(|cage| {
+                    let cells = (0..3).map(|index| cage * 3 + index).collect::<Vec<usize>>();
+                    let sum = cells.iter().map(|cell| digits[*cell]).sum();
+                    Box::new(Cage::new(cells, Some(sum))) as Box<dyn Constraint>
+                })
+                .collect::<Vec<Box<dyn Constraint>>>()
+        };
+        assert_solves(
+            "000000000000000000000000000000009030007000000000000000008090046016054027075086003",
+            SOLUTION,
+            cages,
+        );
+        // No distinct digits fit these sums, though repeated ones would.
+        let empty = "0".repeat(81);
+        let house = (0..9).collect::<Vec<usize>>();
+        for (cells, sum) in [(vec![0, 1, 2], 5), (vec![0, 1], 18), (house, 44)] {
+            let rules = || boxed(Cage::new(cells.clone(), Some(sum)));
+            assert_eq!(solve_both(&empty, rules), [None, None]);
+        }
+        assert_rejects(|| boxed(Cage::new(vec![0, 1], Some(9))));
+    }
+
+    #[test]
+    fn encodes_killer_cages() {
+        let clauses = Cage::new(vec![0, 1, 2], Some(12)).clauses(9).unwrap();
+        for values in assignments(3, 9) {
+            let valid = is_distinct(&values) && values.iter().sum::<usize>() == 12;
+            assert_eq!(accepts(&clauses, &values), valid, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn encodes_arrows() {
+        let clauses = Arrow::new(0, vec![1, 2]).clauses(9).unwrap();
+        for values in assignments(3, 9) {
+            let valid = values[0] == values[1] + values[2];
+            assert_eq!(accepts(&clauses, &values), valid, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn encodes_renban() {
+        let clauses = Line::new(vec![0, 1, 2], Renban).clauses(9).unwrap();
+        for values in assignments(3, 9) {
+            let spread = values.iter().max().unwrap() - values.iter().min().unwrap();
+            let valid = is_distinct(&values) && spread == 2;
+            assert_eq!(accepts(&clauses, &values), valid, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn encodes_region_sums() {
+        let rule = RegionSum::new(vec![0, 0, 1, 1, 2]);
+        let clauses = Line::new(vec![0, 1, 2, 3, 4], rule).clauses(4).unwrap();
+        for values in assignments(5, 4) {
+            let valid = values[0] + values[1] == values[2] + values[3]
+                && values[2] + values[3] == values[4];
+            assert_eq!(accepts(&clauses, &values), valid, "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn encodes_sandwiches() {
+        for total in 0..=6 {
+            let clauses = Sandwich::new(vec![0, 1, 2, 3], total).clauses(4).unwrap();
+            for values in assignments(4, 4).filter(|values| is_distinct(values)) {
+                let crusts = [1, 4].map(|crust| values.iter().position(|value| *value == crust));
+                let (low, high) = (crusts[0].unwrap(), crusts[1].unwrap());
+                let between = &values[low.min(high) + 1..low.max(high)];
+                let valid = between.iter().sum::<usize>() == total as usize;
+                assert_eq!(accepts(&clauses, &values), valid, "{:?}", values);
+            }
+        }
+    }
+
+    #[test]
+    fn names_rules_by_type() {
+        assert_eq!(Cage::new(vec![0], None).name(), "Cage");
+        assert_eq!(Line::new(vec![0], Renban).name(), "Line<Renban>");
+    }
+}
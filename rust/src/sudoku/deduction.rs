@@ -0,0 +1,243 @@
+//! Eliminations beyond singles, run on the per-digit bitboards once singles stall.
+
+use super::list::{ALL81, BIT9};
+use super::BITS_LISTS;
+
+/// Which deductions run after singles. Subset sizes run from pairs up to the given size;
+/// 0 or 1 leaves the stage off.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Stages {
+    /// Pointing and claiming: a digit confined to the overlap of two groups leaves the rest
+    /// of both.
+    pub locked_candidates: bool,
+    /// `n` cells of a group holding only `n` digits between them.
+    pub naked_subsets: usize,
+    /// `n` digits of a group fitting only in `n` of its cells.
+    pub hidden_subsets: usize,
+}
+
+impl Stages {
+    /// Singles only, as the solver has always run.
+    pub fn none() -> Self {
+        Stages::default()
+    }
+
+    /// Locked candidates and naked and hidden subsets up to quads.
+    pub fn all() -> Self {
+        Stages {
+            locked_candidates: true,
+            naked_subsets: 4,
+            hidden_subsets: 4,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.locked_candidates && self.naked_subsets < 2 && self.hidden_subsets < 2
+    }
+}
+
+/// Runs each stage once over the cells of the digits in `numbers`. Returns `None` on a
+/// contradiction, otherwise the cells left to every digit.
+pub(crate) fn deduce(
+    stages: &Stages,
+    groups: &[u128],
+    numbers: usize,
+    number_cells: &[u128; 9],
+) -> Option<[u128; 9]> {
+    let mut cells = *number_cells;
+    if stages.locked_candidates && !locked_candidates(groups, numbers, &mut cells) {
+        return None;
+    }
+    if stages.naked_subsets >= 2
+        && !naked_subsets(groups, numbers, &mut cells, stages.naked_subsets)
+    {
+        return None;
+    }
+    if stages.hidden_subsets >= 2
+        && !hidden_subsets(groups, numbers, &mut cells, stages.hidden_subsets)
+    {
+        return None;
+    }
+    Some(cells)
+}
+
+fn locked_candidates(groups: &[u128], numbers: usize, cells: &mut [u128; 9]) -> bool {
+    for number in BITS_LISTS[numbers].iter() {
+        for (index, group) in groups.iter().enumerate() {
+            let within = cells[*number] & group;
+            if within == 0 {
+                return false;
+            }
+            for (other_index, other) in groups.iter().enumerate() {
+                if other_index != index && within & !other == 0 {
+                    cells[*number] &= !(other & !group);
+                }
+            }
+        }
+    }
+    true
+}
+
+/// The cells of `group` not taken by a placed digit, by position within the group.
+fn free_cells(group: u128, numbers: usize, cells: &[u128; 9]) -> Vec<u128> {
+    let placed = (0..9)
+        .filter(|number| numbers & BIT9[*number] == 0)
+        .fold(0, |placed, number| placed | cells[number]);
+    let mut free = group & !placed & ALL81;
+    let mut positions = vec![];
+    while free != 0 {
+        let cell = free & free.wrapping_neg();
+        positions.push(cell);
+        free ^= cell;
+    }
+    positions
+}
+
+/// The subsets of `0..length` with between 2 and `size` members, as bitmasks, leaving out the
+/// whole set.
+fn subsets(length: usize, size: usize) -> impl Iterator<Item = usize> {
+    (1..(1usize << length) - 1)
+        .filter(move |subset| (2..=size).contains(&BITS_LISTS[*subset].len()))
+}
+
+fn naked_subsets(groups: &[u128], numbers: usize, cells: &mut [u128; 9], size: usize) -> bool {
+    for group in groups.iter() {
+        let free = free_cells(*group, numbers, cells);
+        let mut digits = free
+            .iter()
+            .map(|cell| {
+                BITS_LISTS[numbers]
+                    .iter()
+                    .filter(|number| cells[**number] & cell != 0)
+                    .fold(0, |digits, number| digits | BIT9[*number])
+            })
+            .collect::<Vec<usize>>();
+        for subset in subsets(free.len(), size) {
+            let members = &BITS_LISTS[subset];
+            let union = members
+                .iter()
+                .fold(0, |union, position| union | digits[*position]);
+            let length = BITS_LISTS[union].len();
+            if length < members.len() {
+                return false;
+            } else if length == members.len() {
+                for (position, cell) in free.iter().enumerate() {
+                    if subset & 1 << position == 0 && digits[position] & union != 0 {
+                        digits[position] &= !union;
+                        for number in BITS_LISTS[union].iter() {
+                            cells[*number] &= !cell;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    true
+}
+
+fn hidden_subsets(groups: &[u128], numbers: usize, cells: &mut [u128; 9], size: usize) -> bool {
+    let unplaced = &BITS_LISTS[numbers];
+    for group in groups.iter() {
+        for subset in subsets(unplaced.len(), size) {
+            let members = &BITS_LISTS[subset];
+            let union = members.iter().fold(0, |union, position| {
+                union | (cells[unplaced[*position]] & group)
+            });
+            let length = union.count_ones() as usize;
+            if length < members.len() {
+                return false;
+            } else if length == members.len() {
+                for (position, number) in unplaced.iter().enumerate() {
+                    if subset & 1 << position == 0 {
+                        cells[*number] &= !union;
+                    }
+                }
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::list::GROUPS;
+    use super::*;
+
+    const ROW: u128 = GROUPS[0];
+    const BOX: u128 = GROUPS[18];
+
+    /// Runs `stages` on a grid with every digit unplaced and free everywhere except where
+    /// `restrict` says.
+    fn run(stages: Stages, restrict: impl Fn(&mut [u128; 9])) -> Option<[u128; 9]> {
+        let mut cells = [ALL81; 9];
+        restrict(&mut cells);
+        deduce(&stages, &GROUPS, 511, &cells)
+    }
+
+    #[test]
+    fn points_and_claims_locked_candidates() {
+        let stages = Stages {
+            locked_candidates: true,
+            ..Stages::none()
+        };
+        // The first digit fits only the top row of the first box, so it leaves the rest of
+        // that row.
+        let cells = run(stages, |cells| cells[0] &= !(BOX & !ROW)).unwrap();
+        let mut expected = [ALL81; 9];
+        expected[0] = ALL81 & !(BOX ^ ROW);
+        assert_eq!(cells, expected);
+        // Confined to the first box within the top row, it leaves the rest of that box.
+        let cells = run(stages, |cells| cells[0] &= !(ROW & !BOX)).unwrap();
+        assert_eq!(cells, expected);
+        assert_eq!(run(stages, |cells| cells[0] &= !ROW), None);
+    }
+
+    #[test]
+    fn finds_naked_pairs() {
+        let stages = Stages {
+            naked_subsets: 2,
+            ..Stages::none()
+        };
+        // The first two cells hold only the first two digits, which leave their row and box.
+        let cells = run(stages, |cells| {
+            cells[2..].iter_mut().for_each(|cells| *cells &= !0b11);
+        })
+        .unwrap();
+        let mut expected = [ALL81 & !0b11; 9];
+        expected[0] = ALL81 & !((ROW | BOX) & !0b11);
+        expected[1] = expected[0];
+        assert_eq!(cells, expected);
+        // Three cells left with the first digit alone cannot all take it.
+        let three = |cells: &mut [u128; 9]| {
+            cells[1..].iter_mut().for_each(|cells| *cells &= !0b111);
+        };
+        assert_eq!(run(stages, three), None);
+    }
+
+    #[test]
+    fn finds_hidden_pairs() {
+        let stages = Stages {
+            hidden_subsets: 2,
+            ..Stages::none()
+        };
+        // The first two digits fit only the first two cells of the top row, so no other
+        // digit stays there.
+        let cells = run(stages, |cells| {
+            cells[..2]
+                .iter_mut()
+                .for_each(|cells| *cells &= !(ROW & !0b11));
+        })
+        .unwrap();
+        let mut expected = [ALL81 & !0b11; 9];
+        expected[0] = ALL81 & !(ROW & !0b11);
+        expected[1] = expected[0];
+        assert_eq!(cells, expected);
+        // Three digits fitting only two cells cannot all be placed.
+        let three = |cells: &mut [u128; 9]| {
+            cells[..3]
+                .iter_mut()
+                .for_each(|cells| *cells &= !(ROW & !0b11));
+        };
+        assert_eq!(run(stages, three), None);
+    }
+}
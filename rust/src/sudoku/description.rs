@@ -0,0 +1,417 @@
+use serde::{Deserialize, Serialize};
+
+use super::bits::Bits;
+use super::constraint;
+use super::geometry::Geometry;
+use super::model::Model;
+
+/// A declarative puzzle file. Cells are 0-based indices in reading order, `row * size + col`.
+/// With `grids`, they count along the rows of the canvas holding every grid instead.
+///
+/// ```json
+/// {
+///   "size": 9,
+///   "givens": "000000010400000000020000000000050407008000300001090000300400200050100000000806000",
+///   "cages": [{ "cells": [0, 1], "sum": 10 }],
+///   "lines": [{ "type": "thermo", "cells": [9, 10, 11] }],
+///   "dots": [{ "type": "white", "cells": [20, 21] }]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Description {
+    pub size: usize,
+    /// Box height and width; defaults to the usual shape for `size`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boxes: Option<(usize, usize)>,
+    /// A Latin square without boxes, such as a Futoshiki grid.
+    #[serde(skip_serializing_if = "is_false")]
+    pub latin: bool,
+    /// The top left `[row, col]` corners of overlapping grids, such as `[[0, 0], [6, 6]]` for
+    /// a twodoku; givens then list the cells grid after grid.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub grids: Vec<(usize, usize)>,
+    /// Irregular regions replacing the boxes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Vec<usize>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra_houses: Vec<Vec<usize>>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub givens: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cages: Vec<Cage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<Line>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dots: Vec<Dot>,
+    /// Every orthogonal pair without a dot has neither dot's relation.
+    #[serde(skip_serializing_if = "is_false")]
+    pub negative_dots: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub non_consecutive: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub even: Vec<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub odd: Vec<usize>,
+    /// `(greater, smaller)` pairs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub greater_than: Vec<(usize, usize)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandwiches: Option<Sandwiches>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Default for Description {
+    fn default() -> Self {
+        Description {
+            size: 9,
+            boxes: None,
+            latin: false,
+            grids: vec![],
+            regions: vec![],
+            extra_houses: vec![],
+            givens: String::new(),
+            cages: vec![],
+            lines: vec![],
+            dots: vec![],
+            negative_dots: false,
+            non_consecutive: false,
+            even: vec![],
+            odd: vec![],
+            greater_than: vec![],
+            sandwiches: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Cage {
+    pub cells: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sum: Option<u32>,
+}
+
+/// Line constraints; an arrow starts with its circle.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Line {
+    Thermo { cells: Vec<usize> },
+    Whispers { cells: Vec<usize> },
+    Renban { cells: Vec<usize> },
+    Palindrome { cells: Vec<usize> },
+    RegionSum { cells: Vec<usize> },
+    Arrow { cells: Vec<usize> },
+}
+
+impl Line {
+    pub fn cells(&self) -> &[usize] {
+        match self {
+            Line::Thermo { cells }
+            | Line::Whispers { cells }
+            | Line::Renban { cells }
+            | Line::Palindrome { cells }
+            | Line::RegionSum { cells }
+            | Line::Arrow { cells } => cells,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Dot {
+    White { cells: (usize, usize) },
+    Black { cells: (usize, usize) },
+}
+
+/// Sandwich totals outside the grid, `null` where there is no clue.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sandwiches {
+    pub rows: Vec<Option<u32>>,
+    pub columns: Vec<Option<u32>>,
+}
+
+impl Description {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|error| error.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// The width and height of the canvas holding the grids.
+    fn canvas(&self) -> (usize, usize) {
+        if self.grids.is_empty() {
+            return (self.size, self.size);
+        }
+        let extent = |corner: fn(&(usize, usize)) -> usize| {
+            self.grids.iter().map(corner).max().unwrap_or(0) + self.size
+        };
+        (extent(|(_, left)| *left), extent(|(top, _)| *top))
+    }
+
+    fn covers(&self, cell: usize) -> bool {
+        let (width, height) = self.canvas();
+        if self.grids.is_empty() {
+            return cell < width * height;
+        }
+        let (row, col) = (cell / width, cell % width);
+        self.grids.iter().any(|(top, left)| {
+            (*top..top + self.size).contains(&row) && (*left..left + self.size).contains(&col)
+        })
+    }
+
+    /// Checks that each pair holds two orthogonally adjacent cells.
+    fn check_adjacent<'a>(
+        &self,
+        pairs: impl IntoIterator<Item = &'a (usize, usize)>,
+    ) -> Result<(), String> {
+        let (width, _) = self.canvas();
+        for (a, b) in pairs {
+            let (first, second) = (*a.min(b), *a.max(b));
+            let beside = second - first == 1 && first / width == second / width;
+            if !beside && second - first != width {
+                return Err(format!("cells {} and {} are not adjacent", a, b));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_cells<'a>(&self, cells: impl IntoIterator<Item = &'a usize>) -> Result<(), String> {
+        match cells.into_iter().find(|cell| !self.covers(**cell)) {
+            Some(cell) => Err(format!("cell {} is outside the grid", cell)),
+            None => Ok(()),
+        }
+    }
+
+    /// The region index of each cell, as split up by region sum lines.
+    fn cell_regions(&self) -> Result<Vec<usize>, String> {
+        if self.latin {
+            return Err("region sum lines need regions".into());
+        }
+        if !self.grids.is_empty() {
+            return Err("region sum lines need a single grid".into());
+        }
+        let mut regions = vec![0; self.size * self.size];
+        if self.regions.is_empty() {
+            let (box_rows, box_cols) = self.box_shape()?;
+            for (cell, region) in regions.iter_mut().enumerate() {
+                let (row, col) = (cell / self.size, cell % self.size);
+                *region = (row / box_rows) * box_rows + col / box_cols;
+            }
+        } else {
+            for (index, cells) in self.regions.iter().enumerate() {
+                for cell in cells.iter() {
+                    regions[*cell] = index;
+                }
+            }
+        }
+        Ok(regions)
+    }
+
+    fn box_shape(&self) -> Result<(usize, usize), String> {
+        match self.boxes {
+            Some((box_rows, box_cols)) if box_rows * box_cols == self.size => {
+                Ok((box_rows, box_cols))
+            }
+            Some((box_rows, box_cols)) => Err(format!(
+                "{}x{} boxes do not fit a {}x{} grid",
+                box_rows, box_cols, self.size, self.size
+            )),
+            None => Geometry::boxes_for(self.size)
+                .ok_or_else(|| format!("no default boxes for size {}", self.size)),
+        }
+    }
+
+    pub fn model(&self) -> Result<Model, String> {
+        if self.size == 0 || self.size * self.size > Bits::CAPACITY {
+            return Err(format!("unsupported size {}", self.size));
+        }
+        let size = self.size;
+        let geometry = if !self.grids.is_empty() {
+            if self.latin || !self.regions.is_empty() {
+                return Err("overlapping grids need boxes".into());
+            }
+            if self.sandwiches.is_some() || self.non_consecutive || self.negative_dots {
+                return Err(
+                    "sandwiches, non-consecutive and negative dots need a single grid".into(),
+                );
+            }
+            let (box_rows, box_cols) = self.box_shape()?;
+            Geometry::gattai(box_rows, box_cols, &self.grids)?
+        } else if self.latin {
+            if !self.regions.is_empty() || self.boxes.is_some() {
+                return Err("a Latin square has no boxes or regions".into());
+            }
+            Geometry::latin(size)?
+        } else if self.regions.is_empty() {
+            let (box_rows, box_cols) = self.box_shape()?;
+            Geometry::new(box_rows, box_cols)?
+        } else {
+            if self.regions.len() != size {
+                return Err(format!(
+                    "expected {} regions, found {}",
+                    size,
+                    self.regions.len()
+                ));
+            }
+            Geometry::jigsaw(&self.regions)?
+        };
+        let mut model = Model::new(geometry);
+        for house in self.extra_houses.iter() {
+            model.geometry.add_house(house)?;
+        }
+        if !self.givens.is_empty() {
+            model.givens = model.geometry.parse(&self.givens).ok_or_else(|| {
+                let cells = model.geometry.grids.len() * size * size;
+                format!("givens must be {} cells of 1-{}", cells, size)
+            })?;
+        }
+
+        for cage in self.cages.iter() {
+            self.check_cells(&cage.cells)?;
+            if let Some(cell) = cage
+                .cells
+                .iter()
+                .enumerate()
+                .find_map(|(index, cell)| cage.cells[..index].contains(cell).then_some(cell))
+            {
+                return Err(format!("cell {} appears twice in a cage", cell));
+            }
+            model.add(constraint::Cage::new(cage.cells.clone(), cage.sum));
+        }
+        for line in self.lines.iter() {
+            self.check_cells(line.cells())?;
+            let cells = line.cells().to_vec();
+            match line {
+                Line::Thermo { .. } => model.add(constraint::Thermo::new(cells)),
+                Line::Whispers { .. } => {
+                    model.add(constraint::Line::new(cells, constraint::GermanWhispers))
+                }
+                Line::Renban { .. } => model.add(constraint::Line::new(cells, constraint::Renban)),
+                Line::Palindrome { .. } => {
+                    model.add(constraint::Line::new(cells, constraint::Palindrome))
+                }
+                Line::RegionSum { .. } => model.add(constraint::Line::new(
+                    cells,
+                    constraint::RegionSum::new(self.cell_regions()?),
+                )),
+                Line::Arrow { .. } if cells.len() > 1 => {
+                    model.add(constraint::Arrow::new(cells[0], cells[1..].to_vec()))
+                }
+                Line::Arrow { .. } => return Err("an arrow needs a circle and a shaft".into()),
+            }
+        }
+        if !self.dots.is_empty() || self.negative_dots {
+            let (mut white, mut black) = (vec![], vec![]);
+            for dot in self.dots.iter() {
+                match *dot {
+                    Dot::White { cells } => white.push(cells),
+                    Dot::Black { cells } => black.push(cells),
+                }
+            }
+            self.check_cells(white.iter().chain(black.iter()).flat_map(|(a, b)| [a, b]))?;
+            self.check_adjacent(white.iter().chain(black.iter()))?;
+            model.add(constraint::Kropki::new(
+                size,
+                white,
+                black,
+                self.negative_dots,
+            ));
+        }
+        if self.non_consecutive {
+            model.add(constraint::non_consecutive(size));
+        }
+        let odd_digits = (0..size)
+            .step_by(2)
+            .fold(0, |digits, number| digits | 1 << number);
+        let all_digits = u32::MAX >> (32 - size);
+        for (cells, digits) in [
+            (&self.even, all_digits & !odd_digits),
+            (&self.odd, odd_digits),
+        ] {
+            if !cells.is_empty() {
+                self.check_cells(cells.iter())?;
+                model.add(constraint::Allowed::new(cells.clone(), digits));
+            }
+        }
+        if !self.greater_than.is_empty() {
+            self.check_cells(self.greater_than.iter().flat_map(|(a, b)| [a, b]))?;
+            self.check_adjacent(self.greater_than.iter())?;
+            model.add(constraint::GreaterThan::new(self.greater_than.clone()));
+        }
+        if let Some(sandwiches) = &self.sandwiches {
+            for (name, totals) in [("rows", &sandwiches.rows), ("columns", &sandwiches.columns)] {
+                if totals.len() > size {
+                    return Err(format!(
+                        "expected at most {} sandwich {}, found {}",
+                        size,
+                        name,
+                        totals.len()
+                    ));
+                }
+            }
+            for (row, total) in sandwiches.rows.iter().enumerate() {
+                if let Some(total) = total {
+                    let cells = (row * size..row * size + size).collect();
+                    model.add(constraint::Sandwich::new(cells, *total));
+                }
+            }
+            for (col, total) in sandwiches.columns.iter().enumerate() {
+                if let Some(total) = total {
+                    let cells = (col..size * size).step_by(size).collect();
+                    model.add(constraint::Sandwich::new(cells, *total));
+                }
+            }
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(json: &str) -> String {
+        Description::from_json(json).unwrap().model().err().unwrap()
+    }
+
+    #[test]
+    fn rejects_malformed_clues() {
+        assert_eq!(
+            error(r#"{ "cages": [{ "cells": [0, 1, 0], "sum": 10 }] }"#),
+            "cell 0 appears twice in a cage"
+        );
+        assert_eq!(
+            error(r#"{ "grids": [[0, 0], [6, 6]], "givens": "0" }"#),
+            "givens must be 162 cells of 1-9"
+        );
+        assert_eq!(
+            error(r#"{ "size": 4, "sandwiches": { "columns": [1, 2, 3, 4, 5] } }"#),
+            "expected at most 4 sandwich columns, found 5"
+        );
+        assert_eq!(
+            error(r#"{ "dots": [{ "type": "white", "cells": [8, 9] }] }"#),
+            "cells 8 and 9 are not adjacent"
+        );
+        assert_eq!(
+            error(r#"{ "dots": [{ "type": "black", "cells": [0, 10] }] }"#),
+            "cells 0 and 10 are not adjacent"
+        );
+        assert_eq!(
+            error(r#"{ "greater_than": [[3, 0]] }"#),
+            "cells 3 and 0 are not adjacent"
+        );
+        let description = r#"{
+            "grids": [[0, 0], [6, 6]],
+            "dots": [{ "type": "white", "cells": [128, 129] }],
+            "greater_than": [[80, 95], [95, 94]]
+        }"#;
+        assert!(Description::from_json(description).unwrap().model().is_ok());
+    }
+}
@@ -0,0 +1,244 @@
+//! DIMACS CNF export of a model, and SAT solver models read back as solutions.
+//!
+//! Variable `cell * size + digit` is true when canvas cell `cell` (row times canvas width
+//! plus column) holds `digit`, for digits `1..=size`. Canvas cells outside every grid of the
+//! geometry keep their variables unused. The auxiliary variables of the constraint encodings,
+//! such as running sums, come after those of the cells.
+
+use super::constraint::{Clause, Var};
+use super::model::Model;
+
+fn variable(size: usize, cell: usize, digit: usize) -> i64 {
+    (cell * size + digit + 1) as i64
+}
+
+/// The clauses of the model in literal form: one digit per cell, each digit once per house,
+/// the givens, then the variant constraints. Also returns the number of variables.
+fn clauses(model: &Model) -> Result<(Vec<Vec<i64>>, usize), String> {
+    let geometry = &model.geometry;
+    let size = geometry.size;
+    let mut clauses = vec![];
+    let mut exactly_one = |literals: Vec<i64>| {
+        for (index, first) in literals.iter().enumerate() {
+            for second in literals.iter().skip(index + 1) {
+                clauses.push(vec![-first, -second]);
+            }
+        }
+        clauses.push(literals);
+    };
+    for cell in geometry.all.ones() {
+        exactly_one((0..size).map(|digit| variable(size, cell, digit)).collect());
+    }
+    for house in geometry.houses.iter() {
+        for digit in 0..size {
+            exactly_one(
+                house
+                    .ones()
+                    .map(|cell| variable(size, cell, digit))
+                    .collect(),
+            );
+        }
+    }
+    for (cell, value) in model.givens.iter().enumerate() {
+        if *value > 0 {
+            clauses.push(vec![variable(size, cell, value - 1)]);
+        }
+    }
+    let mut variables = geometry.cells * size;
+    for (index, constraint) in model.constraints.iter().enumerate() {
+        let constraint_clauses: Vec<Clause> = constraint.clauses(size).ok_or_else(|| {
+            format!(
+                "constraint {} ({}) has no CNF encoding",
+                index + 1,
+                constraint.name()
+            )
+        })?;
+        let mut auxiliaries = 0;
+        clauses.extend(constraint_clauses.iter().map(|clause| {
+            clause
+                .iter()
+                .map(|(var, holds)| {
+                    let literal = match *var {
+                        Var::Digit(cell, digit) => variable(size, cell, digit),
+                        Var::Aux(index) => {
+                            auxiliaries = auxiliaries.max(index + 1);
+                            (variables + index + 1) as i64
+                        }
+                    };
+                    if *holds {
+                        literal
+                    } else {
+                        -literal
+                    }
+                })
+                .collect()
+        }));
+        variables += auxiliaries;
+    }
+    Ok((clauses, variables))
+}
+
+/// The model as a DIMACS CNF file, its comment lines giving the variable mapping.
+pub fn export(model: &Model) -> Result<String, String> {
+    let geometry = &model.geometry;
+    let (clauses, variables) = clauses(model)?;
+    let mut cnf = format!(
+        "c {}\n\
+         c variable cell * {} + digit: canvas cell (row * {} + column) holds digit 1-{}\n",
+        model.puzzle(),
+        geometry.size,
+        geometry.width,
+        geometry.size
+    );
+    let cell_variables = geometry.cells * geometry.size;
+    if variables > cell_variables {
+        cnf.push_str(&format!(
+            "c variables from {} on are auxiliary\n",
+            cell_variables + 1
+        ));
+    }
+    cnf.push_str(&format!("p cnf {} {}\n", variables, clauses.len()));
+    for clause in clauses.iter() {
+        for literal in clause.iter() {
+            cnf.push_str(&literal.to_string());
+            cnf.push(' ');
+        }
+        cnf.push_str("0\n");
+    }
+    Ok(cnf)
+}
+
+/// Reads a SAT solver's output for the CNF of `model` back into a solution, formatted like
+/// `Geometry::format`. Takes both the competition format (`s SATISFIABLE` then `v` lines) and
+/// the MiniSat one (`SAT` then the literals).
+pub fn read_model(text: &str, model: &Model) -> Result<String, String> {
+    let geometry = &model.geometry;
+    let size = geometry.size;
+    let mut literals = vec![];
+    for line in text.lines().map(str::trim) {
+        let line = match line.split_once(' ') {
+            Some(("v", rest)) => rest,
+            _ if line.is_empty() || line.starts_with('c') => continue,
+            _ if line.contains("UNSAT") => return Err("the solver found no model".into()),
+            _ if line.starts_with('s') || line == "SAT" => continue,
+            _ => line,
+        };
+        for literal in line.split_whitespace() {
+            let literal = literal
+                .parse::<i64>()
+                .map_err(|_| format!("{} is not a literal", literal))?;
+            literals.push(literal);
+        }
+    }
+
+    let (_, variables) = clauses(model)?;
+    let mut values = vec![0; geometry.cells];
+    for literal in literals.into_iter().filter(|literal| *literal > 0) {
+        let variable = literal as usize - 1;
+        let (cell, digit) = (variable / size, variable % size + 1);
+        if variable >= variables {
+            return Err(format!("variable {} is out of range", literal));
+        }
+        if cell >= geometry.cells {
+            continue;
+        }
+        if values[cell] > 0 {
+            return Err(format!(
+                "cell {} holds both {} and {}",
+                cell, values[cell], digit
+            ));
+        }
+        values[cell] = digit;
+    }
+    for cell in geometry.all.ones() {
+        if values[cell] == 0 {
+            return Err(format!("cell {} holds no digit", cell));
+        }
+        if model.givens[cell] > 0 && model.givens[cell] != values[cell] {
+            return Err(format!(
+                "cell {} holds {} instead of the given {}",
+                cell, values[cell], model.givens[cell]
+            ));
+        }
+    }
+    Ok(geometry.format(&values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::constraint::{Cage, Constraint};
+    use crate::sudoku::Geometry;
+
+    /// A 2x2 Latin square with 1 given in its first cell: variables 1-8, two per cell.
+    fn latin() -> Model {
+        let mut model = Model::new(Geometry::latin(2).unwrap());
+        model.givens[0] = 1;
+        model
+    }
+
+    /// The variable count from the `p cnf` line of an export.
+    fn declared(cnf: &str) -> usize {
+        let header = cnf.lines().find(|line| line.starts_with("p cnf")).unwrap();
+        header.split_whitespace().nth(2).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn reads_both_formats() {
+        let model = latin();
+        let competition = "c a comment\ns SATISFIABLE\nv 1 -2 -3 4 -5 6\nv 7 -8 0\n";
+        assert_eq!(read_model(competition, &model).as_deref(), Ok("1221"));
+        let minisat = "SAT\n1 -2 -3 4 -5 6 7 -8 0\n";
+        assert_eq!(read_model(minisat, &model).as_deref(), Ok("1221"));
+    }
+
+    #[test]
+    fn rejects_bad_models() {
+        let model = latin();
+        let errors = [
+            ("s UNSATISFIABLE\n", "the solver found no model"),
+            ("v 1 x 0\n", "x is not a literal"),
+            ("v 1 4 6 7 9 0\n", "variable 9 is out of range"),
+            ("v 1 2 4 6 7 0\n", "cell 0 holds both 1 and 2"),
+            ("v 1 4 6 0\n", "cell 3 holds no digit"),
+            ("v 2 3 5 8 0\n", "cell 0 holds 2 instead of the given 1"),
+        ];
+        for (text, error) in errors {
+            assert_eq!(read_model(text, &model), Err(error.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn numbers_auxiliaries_after_cells() {
+        let mut model = latin();
+        model.add(Cage::new(vec![0, 1], Some(3)));
+        let cnf = export(&model).unwrap();
+        assert!(cnf.contains("c variables from 9 on are auxiliary\n"));
+        let variables = declared(&cnf);
+        assert!(variables > 8);
+        let text = format!("v 1 4 6 7 {} 0\n", variables);
+        assert_eq!(read_model(&text, &model).as_deref(), Ok("1221"));
+        let text = format!("v 1 4 6 7 {} 0\n", variables + 1);
+        assert!(read_model(&text, &model).is_err());
+        assert_eq!(declared(&export(&latin()).unwrap()), 8);
+    }
+
+    struct Opaque;
+
+    impl Constraint for Opaque {
+        fn propagate(&self, _candidates: &mut [u32]) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn names_constraints_without_encoding() {
+        let mut model = latin();
+        model.add(Cage::new(vec![0, 1], None));
+        model.add(Opaque);
+        assert_eq!(
+            export(&model),
+            Err("constraint 2 (Opaque) has no CNF encoding".to_string())
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Dancing links: a puzzle as an exact cover problem, built from the houses of any
+//! `Geometry`. Slower than the bitboards, but simple enough to serve as a reference.
+
+use super::geometry::Geometry;
+
+/// The exact cover matrix of a geometry: one column per cell and per house and digit, one
+/// row per cell and digit. Rows are numbered `position * size + digit`, positions counting
+/// the cells of the geometry in order.
+struct Matrix {
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    row: Vec<usize>,
+    size: Vec<usize>,
+    /// The first node of each row.
+    first: Vec<usize>,
+}
+
+const ROOT: usize = 0;
+
+impl Matrix {
+    fn new(geometry: &Geometry, cells: &[usize]) -> Self {
+        let columns = cells.len() + geometry.houses.len() * geometry.size;
+        let mut matrix = Matrix {
+            left: (0..=columns)
+                .map(|node| (node + columns) % (columns + 1))
+                .collect(),
+            right: (0..=columns)
+                .map(|node| (node + 1) % (columns + 1))
+                .collect(),
+            up: (0..=columns).collect(),
+            down: (0..=columns).collect(),
+            column: (0..=columns).collect(),
+            row: vec![usize::MAX; columns + 1],
+            size: vec![0; columns + 1],
+            first: vec![],
+        };
+        for (position, cell) in cells.iter().enumerate() {
+            let houses = geometry
+                .houses
+                .iter()
+                .enumerate()
+                .filter(|(_, house)| house.has(*cell))
+                .map(|(index, _)| index)
+                .collect::<Vec<usize>>();
+            for digit in 0..geometry.size {
+                let mut row_columns = vec![1 + position];
+                row_columns.extend(
+                    houses
+                        .iter()
+                        .map(|house| 1 + cells.len() + house * geometry.size + digit),
+                );
+                matrix.add_row(position * geometry.size + digit, &row_columns);
+            }
+        }
+        matrix
+    }
+
+    fn add_row(&mut self, row: usize, columns: &[usize]) {
+        let first = self.column.len();
+        assert_eq!(self.first.len(), row);
+        self.first.push(first);
+        for (index, column) in columns.iter().enumerate() {
+            let node = first + index;
+            self.column.push(*column);
+            self.row.push(row);
+            self.up.push(self.up[*column]);
+            self.down.push(*column);
+            let above = self.up[*column];
+            self.down[above] = node;
+            self.up[*column] = node;
+            self.size[*column] += 1;
+            self.left.push(if index == 0 { node } else { node - 1 });
+            self.right.push(first);
+            if index > 0 {
+                self.right[node - 1] = node;
+                self.left[first] = node;
+            }
+        }
+    }
+
+    fn cover(&mut self, column: usize) {
+        self.right[self.left[column]] = self.right[column];
+        self.left[self.right[column]] = self.left[column];
+        let mut node = self.down[column];
+        while node != column {
+            let mut other = self.right[node];
+            while other != node {
+                self.down[self.up[other]] = self.down[other];
+                self.up[self.down[other]] = self.up[other];
+                self.size[self.column[other]] -= 1;
+                other = self.right[other];
+            }
+            node = self.down[node];
+        }
+    }
+
+    fn uncover(&mut self, column: usize) {
+        let mut node = self.up[column];
+        while node != column {
+            let mut other = self.left[node];
+            while other != node {
+                self.size[self.column[other]] += 1;
+                self.down[self.up[other]] = other;
+                self.up[self.down[other]] = other;
+                other = self.left[other];
+            }
+            node = self.up[node];
+        }
+        self.right[self.left[column]] = column;
+        self.left[self.right[column]] = column;
+    }
+
+    /// Covers the other columns of the row of `node`, its own being covered already.
+    fn select(&mut self, node: usize) {
+        let mut other = self.right[node];
+        while other != node {
+            self.cover(self.column[other]);
+            other = self.right[other];
+        }
+    }
+
+    fn unselect(&mut self, node: usize) {
+        let mut other = self.left[node];
+        while other != node {
+            self.uncover(self.column[other]);
+            other = self.left[other];
+        }
+    }
+
+    /// Places a given, or returns `false` when one of its columns is already covered.
+    fn give(&mut self, row: usize) -> bool {
+        let first = self.first[row];
+        let mut node = first;
+        loop {
+            let column = self.column[node];
+            if self.right[self.left[column]] != column {
+                return false;
+            }
+            self.cover(column);
+            node = self.right[node];
+            if node == first {
+                return true;
+            }
+        }
+    }
+
+    /// The column with the fewest rows left, or `None` once all are covered.
+    fn shortest_column(&self) -> Option<usize> {
+        let mut shortest = None;
+        let mut column = self.right[ROOT];
+        while column != ROOT {
+            let shorter = match shortest {
+                Some(best) => self.size[column] < self.size[best],
+                None => true,
+            };
+            if shorter {
+                shortest = Some(column);
+            }
+            column = self.right[column];
+        }
+        shortest
+    }
+
+    /// Algorithm X on an explicit stack of chosen rows, calling `found` with the rows of each
+    /// cover until it returns `false`.
+    fn search(&mut self, mut found: impl FnMut(&[usize]) -> bool) {
+        let mut chosen: Vec<usize> = vec![];
+        loop {
+            let mut next = match self.shortest_column() {
+                None => {
+                    let rows = chosen
+                        .iter()
+                        .map(|node| self.row[*node])
+                        .collect::<Vec<_>>();
+                    if !found(&rows) {
+                        return;
+                    }
+                    None
+                }
+                Some(column) if self.size[column] == 0 => None,
+                Some(column) => {
+                    self.cover(column);
+                    Some(self.down[column])
+                }
+            };
+            loop {
+                match next {
+                    Some(node) if node != self.column[node] => {
+                        self.select(node);
+                        chosen.push(node);
+                        break;
+                    }
+                    Some(column) => self.uncover(column),
+                    None => {}
+                }
+                let Some(node) = chosen.pop() else {
+                    return;
+                };
+                self.unselect(node);
+                next = Some(self.down[node]);
+            }
+        }
+    }
+}
+
+/// The exact cover engine for any geometry.
+pub struct Dlx {
+    pub(crate) geometry: Geometry,
+    cells: Vec<usize>,
+}
+
+impl Dlx {
+    pub fn new(geometry: Geometry) -> Self {
+        let cells = geometry.all.ones().collect();
+        Dlx { geometry, cells }
+    }
+
+    /// Calls `found` with the cell values of each solution until it returns `false`. Returns
+    /// `None` when the puzzle cannot be read.
+    pub fn for_each_solution(
+        &self,
+        puzzle: &str,
+        mut found: impl FnMut(&[usize]) -> bool,
+    ) -> Option<()> {
+        let values = self.geometry.parse(puzzle)?;
+        let size = self.geometry.size;
+        let mut matrix = Matrix::new(&self.geometry, &self.cells);
+        for (position, cell) in self.cells.iter().enumerate() {
+            if values[*cell] > 0 && !matrix.give(position * size + values[*cell] - 1) {
+                return Some(());
+            }
+        }
+        matrix.search(|rows| {
+            let mut solved = values.clone();
+            for row in rows.iter() {
+                solved[self.cells[row / size]] = row % size + 1;
+            }
+            found(&solved)
+        });
+        Some(())
+    }
+
+    pub fn solve(&self, puzzle: &str) -> Option<String> {
+        let mut solution = None;
+        self.for_each_solution(puzzle, |values| {
+            solution = Some(self.geometry.format(values));
+            false
+        })?;
+        solution
+    }
+
+    /// The number of solutions, counting no further than `limit`.
+    pub fn count(&self, puzzle: &str, limit: usize) -> Option<usize> {
+        let mut count = 0;
+        if limit > 0 {
+            self.for_each_solution(puzzle, |_| {
+                count += 1;
+                count < limit
+            })?;
+        }
+        Some(count)
+    }
+
+    /// The solutions, no more than `limit` of them.
+    pub fn solutions(&self, puzzle: &str, limit: usize) -> Option<Vec<String>> {
+        let mut solutions = vec![];
+        if limit > 0 {
+            self.for_each_solution(puzzle, |values| {
+                solutions.push(self.geometry.format(values));
+                solutions.len() < limit
+            })?;
+        }
+        Some(solutions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::count_in;
+    use super::*;
+
+    const PUZZLE: &str =
+        "000000008003000400090020060000079000000061200060502070008000500010000020405000003";
+    const SOLUTION: &str =
+        "621943758783615492594728361142879635357461289869532174238197546916354827475286913";
+
+    fn empty(geometry: &Geometry) -> String {
+        "0".repeat(geometry.grids.iter().flatten().count())
+    }
+
+    #[test]
+    fn counts_empty_grids() {
+        for (geometry, solutions) in [
+            (Geometry::latin(3).unwrap(), 12),
+            (Geometry::latin(4).unwrap(), 576),
+            (Geometry::new(2, 2).unwrap(), 288),
+        ] {
+            let puzzle = empty(&geometry);
+            assert_eq!(
+                Dlx::new(geometry).count(&puzzle, usize::MAX),
+                Some(solutions)
+            );
+        }
+    }
+
+    #[test]
+    fn solves_classic_puzzles() {
+        let dlx = Dlx::new(Geometry::classic());
+        assert_eq!(dlx.solve(PUZZLE).as_deref(), Some(SOLUTION));
+        assert_eq!(
+            dlx.solve(&PUZZLE.replace('0', ".")).as_deref(),
+            Some(SOLUTION)
+        );
+        assert_eq!(dlx.count(PUZZLE, usize::MAX), Some(1));
+        assert_eq!(dlx.solutions(PUZZLE, 2), Some(vec![SOLUTION.to_string()]));
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let geometry = Geometry::new(2, 2).unwrap();
+        let puzzle = empty(&geometry);
+        let dlx = Dlx::new(geometry);
+        assert_eq!(dlx.count(&puzzle, 0), Some(0));
+        assert_eq!(dlx.count(&puzzle, 10), Some(10));
+        let solutions = dlx.solutions(&puzzle, 5).unwrap();
+        assert_eq!(solutions.len(), 5);
+        for solution in solutions.iter() {
+            assert_eq!(dlx.solutions(solution, 2), Some(vec![solution.clone()]));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_and_contradictory_puzzles() {
+        let dlx = Dlx::new(Geometry::classic());
+        assert_eq!(dlx.count(&PUZZLE[1..], usize::MAX), None);
+        assert_eq!(dlx.solve(&PUZZLE.replace('4', "x")), None);
+        let clash = format!("88{}", &PUZZLE[2..]);
+        assert_eq!(dlx.count(&clash, usize::MAX), Some(0));
+        assert_eq!(dlx.solve(&clash), None);
+    }
+
+    #[test]
+    fn agrees_with_the_wide_boards() {
+        for geometry in [Geometry::new(2, 3).unwrap(), Geometry::twodoku()] {
+            let dlx = Dlx::new(geometry.clone());
+            let solution = dlx.solve(&empty(&geometry)).unwrap();
+            let puzzle = solution
+                .chars()
+                .enumerate()
+                .map(|(index, digit)| if index % 3 == 0 { '0' } else { digit })
+                .collect::<String>();
+            let solutions = dlx.count(&puzzle, usize::MAX);
+            assert!(solutions > Some(1));
+            assert_eq!(solutions, count_in(&puzzle, &geometry, usize::MAX));
+        }
+    }
+}
@@ -0,0 +1,534 @@
+//! The f-puzzles JSON format, which SudokuPad also loads, usually lz-string compressed into
+//! a `?load=` or `/fpuzzles` URL. Cells are written `R1C1`, 1-based.
+
+use serde_json::{json, Map, Value};
+
+use super::description::{Cage, Description, Dot, Line, Sandwiches};
+use super::geometry::Geometry;
+use super::lz_string;
+
+/// Keys which carry no constraint.
+const METADATA: [&str; 6] = ["size", "grid", "title", "author", "ruleset", "solution"];
+
+/// Editor settings and the setter's decorations, which draw on the grid without adding a rule.
+const COSMETIC: [&str; 8] = [
+    "highlightConflicts",
+    "disabledlogic",
+    "truecandidatesoptions",
+    "text",
+    "line",
+    "rectangle",
+    "circle",
+    "cage",
+];
+
+/// Reads raw f-puzzles JSON, its lz-string compressed form, or a URL ending in either.
+pub fn import(text: &str) -> Result<Description, String> {
+    let text = text.trim();
+    let json = if text.starts_with('{') {
+        text.to_string()
+    } else {
+        let compressed = match (text.rfind("load="), text.rfind("fpuzzles")) {
+            (Some(index), _) => &text[index + "load=".len()..],
+            (None, Some(index)) => &text[index + "fpuzzles".len()..],
+            (None, None) => text,
+        };
+        let compressed = compressed.split('&').next().unwrap();
+        lz_string::decompress_from_base64(compressed)
+            .filter(|json| json.starts_with('{'))
+            .ok_or("not an f-puzzles string")?
+    };
+    let puzzle: Map<String, Value> =
+        serde_json::from_str(&json).map_err(|error| error.to_string())?;
+    from_fpuzzles(&puzzle)
+}
+
+/// The puzzle as f-puzzles JSON, with the `solution` filled in when one is given.
+pub fn export(description: &Description, solution: Option<&str>) -> Result<String, String> {
+    Ok(Value::Object(to_fpuzzles(description, solution)?).to_string())
+}
+
+/// [`export`], compressed the way puzzle URLs carry it.
+pub fn export_compressed(
+    description: &Description,
+    solution: Option<&str>,
+) -> Result<String, String> {
+    export(description, solution).map(|json| lz_string::compress_to_base64(&json))
+}
+
+/// The 1-based `(row, col)` of an `R1C1` name; clues outside the grid use row or column 0.
+fn parse_position(value: &Value) -> Option<(usize, usize)> {
+    let name = value.as_str()?.to_ascii_uppercase();
+    let (row, col) = name.strip_prefix('R')?.split_once('C')?;
+    Some((row.parse().ok()?, col.parse().ok()?))
+}
+
+fn parse_cell(value: &Value, size: usize) -> Result<usize, String> {
+    parse_position(value)
+        .filter(|(row, col)| (1..=size).contains(row) && (1..=size).contains(col))
+        .map(|(row, col)| (row - 1) * size + col - 1)
+        .ok_or_else(|| format!("bad cell {}", value))
+}
+
+fn parse_cells(value: &Value, size: usize) -> Result<Vec<usize>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("expected a list of cells, found {}", value))?
+        .iter()
+        .map(|cell| parse_cell(cell, size))
+        .collect()
+}
+
+fn cell_name(cell: usize, size: usize) -> String {
+    format!("R{}C{}", cell / size + 1, cell % size + 1)
+}
+
+fn cell_names(cells: &[usize], size: usize) -> Vec<String> {
+    cells.iter().map(|cell| cell_name(*cell, size)).collect()
+}
+
+/// A clue value, which f-puzzles writes as a string or a number; `None` when left blank.
+fn parse_value(value: Option<&Value>) -> Result<Option<u32>, String> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) => number
+            .as_u64()
+            .map(|number| Some(number as u32))
+            .ok_or_else(|| format!("bad value {}", number)),
+        Some(Value::String(text)) if text.is_empty() => Ok(None),
+        Some(Value::String(text)) => text
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("bad value {}", text)),
+        Some(other) => Err(format!("bad value {}", other)),
+    }
+}
+
+fn objects<'a>(puzzle: &'a Map<String, Value>, key: &str) -> Result<&'a [Value], String> {
+    match puzzle.get(key) {
+        None => Ok(&[]),
+        Some(Value::Array(items)) => Ok(items),
+        Some(_) => Err(format!("{} must be a list", key)),
+    }
+}
+
+/// The other keys read by [`import`].
+const CONSTRAINTS: [&str; 11] = [
+    "killercage",
+    "arrow",
+    "difference",
+    "ratio",
+    "negative",
+    "extraregion",
+    "disjointgroups",
+    "nonconsecutive",
+    "odd",
+    "even",
+    "sandwichsum",
+];
+
+fn is_known(key: &str) -> bool {
+    [&METADATA[..], &COSMETIC[..], &LINES[..], &CONSTRAINTS[..]]
+        .iter()
+        .any(|keys| keys.contains(&key))
+}
+
+fn flag(puzzle: &Map<String, Value>, key: &str) -> bool {
+    puzzle.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+/// The line constraints stored as `{ "lines": [[cells]] }`, with the f-puzzles key of each.
+const LINES: [&str; 5] = [
+    "thermometer",
+    "whispers",
+    "renban",
+    "palindrome",
+    "regionsumline",
+];
+
+fn to_line(key: &str, cells: Vec<usize>) -> Line {
+    match key {
+        "thermometer" => Line::Thermo { cells },
+        "whispers" => Line::Whispers { cells },
+        "renban" => Line::Renban { cells },
+        "palindrome" => Line::Palindrome { cells },
+        _ => Line::RegionSum { cells },
+    }
+}
+
+fn from_fpuzzles(puzzle: &Map<String, Value>) -> Result<Description, String> {
+    let size = puzzle
+        .get("size")
+        .and_then(Value::as_u64)
+        .ok_or("missing size")? as usize;
+    let (box_rows, box_cols) =
+        Geometry::boxes_for(size).ok_or_else(|| format!("unsupported size {}", size))?;
+    let mut description = Description {
+        size,
+        ..Description::default()
+    };
+
+    let grid = objects(puzzle, "grid")?;
+    if grid.len() != size {
+        return Err(format!("expected {} rows, found {}", size, grid.len()));
+    }
+    let mut givens = vec!['0'; size * size];
+    let mut regions = vec![vec![]; size];
+    let mut irregular = false;
+    for (row, cells) in grid.iter().enumerate() {
+        let cells = cells.as_array().filter(|cells| cells.len() == size);
+        for (col, cell) in cells.ok_or("grid rows must be full")?.iter().enumerate() {
+            let index = row * size + col;
+            if cell.get("given").and_then(Value::as_bool).unwrap_or(false) {
+                givens[index] = cell
+                    .get("value")
+                    .and_then(Value::as_u64)
+                    .filter(|value| (1..=size as u64).contains(value))
+                    .and_then(|value| char::from_digit(value as u32, 36))
+                    .ok_or_else(|| format!("bad given in {}", cell_name(index, size)))?
+                    .to_ascii_uppercase();
+            }
+            let standard = (row / box_rows) * box_rows + col / box_cols;
+            let region = match cell.get("region") {
+                None => standard,
+                Some(region) => region
+                    .as_u64()
+                    .map(|region| region as usize)
+                    .filter(|region| *region < size)
+                    .ok_or_else(|| format!("bad region in {}", cell_name(index, size)))?,
+            };
+            irregular |= region != standard;
+            regions[region].push(index);
+        }
+    }
+    if givens.iter().any(|given| *given != '0') {
+        description.givens = givens.into_iter().collect();
+    }
+    if irregular {
+        description.regions = regions;
+    }
+
+    for cage in objects(puzzle, "killercage")? {
+        description.cages.push(Cage {
+            cells: parse_cells(&cage["cells"], size)?,
+            sum: parse_value(cage.get("value"))?,
+        });
+    }
+    for key in LINES {
+        for item in objects(puzzle, key)? {
+            if key == "whispers" && !matches!(parse_value(item.get("value"))?, None | Some(5)) {
+                return Err("only whispers with a difference of 5 are supported".into());
+            }
+            for line in objects(item.as_object().unwrap_or(&Map::new()), "lines")? {
+                description
+                    .lines
+                    .push(to_line(key, parse_cells(line, size)?));
+            }
+        }
+    }
+    for arrow in objects(puzzle, "arrow")? {
+        let circle = parse_cells(&arrow["cells"], size)?;
+        if circle.len() != 1 {
+            return Err("only single cell arrow circles are supported".into());
+        }
+        let mut cells = circle.clone();
+        for line in arrow["lines"].as_array().ok_or("arrow without lines")? {
+            let line = parse_cells(line, size)?;
+            cells.extend(line.into_iter().filter(|cell| *cell != circle[0]));
+        }
+        description.lines.push(Line::Arrow { cells });
+    }
+    for (key, default) in [("difference", 1), ("ratio", 2)] {
+        for dot in objects(puzzle, key)? {
+            if parse_value(dot.get("value"))?.unwrap_or(default) != default {
+                return Err(format!("only {} dots of {} are supported", key, default));
+            }
+            let cells = match parse_cells(&dot["cells"], size)?[..] {
+                [first, second] => (first, second),
+                _ => return Err("a dot joins two cells".into()),
+            };
+            description.dots.push(match key {
+                "difference" => Dot::White { cells },
+                _ => Dot::Black { cells },
+            });
+        }
+    }
+    let negative = objects(puzzle, "negative")?;
+    match negative.len() {
+        0 => {}
+        2 if negative.contains(&json!("ratio")) && negative.contains(&json!("difference")) => {
+            description.negative_dots = true
+        }
+        _ => return Err("only the negative constraint for both dots is supported".into()),
+    }
+    for region in objects(puzzle, "extraregion")? {
+        description
+            .extra_houses
+            .push(parse_cells(&region["cells"], size)?);
+    }
+    if flag(puzzle, "disjointgroups") {
+        if irregular {
+            return Err("disjoint groups need regular boxes".into());
+        }
+        for position in 0..size {
+            let (top, left) = (position / box_cols, position % box_cols);
+            description.extra_houses.push(
+                (0..size)
+                    .map(|index| {
+                        let row = (index / box_rows) * box_rows + top;
+                        let col = (index % box_rows) * box_cols + left;
+                        row * size + col
+                    })
+                    .collect(),
+            );
+        }
+    }
+    description.non_consecutive = flag(puzzle, "nonconsecutive");
+    for cell in objects(puzzle, "odd")? {
+        description.odd.push(parse_cell(&cell["cell"], size)?);
+    }
+    for cell in objects(puzzle, "even")? {
+        description.even.push(parse_cell(&cell["cell"], size)?);
+    }
+    for clue in objects(puzzle, "sandwichsum")? {
+        let total = parse_value(clue.get("value"))?;
+        let sandwiches = description.sandwiches.get_or_insert_with(|| Sandwiches {
+            rows: vec![None; size],
+            columns: vec![None; size],
+        });
+        match parse_position(&clue["cell"]) {
+            Some((0, col)) if (1..=size).contains(&col) => sandwiches.columns[col - 1] = total,
+            Some((row, 0)) if (1..=size).contains(&row) => sandwiches.rows[row - 1] = total,
+            _ => return Err(format!("bad sandwich clue at {}", clue["cell"])),
+        }
+    }
+
+    match puzzle
+        .iter()
+        .find(|(key, value)| !is_known(key) && !is_empty(value))
+    {
+        Some((key, _)) => Err(format!("unsupported f-puzzles constraint {}", key)),
+        None => Ok(description),
+    }
+}
+
+fn to_fpuzzles(
+    description: &Description,
+    solution: Option<&str>,
+) -> Result<Map<String, Value>, String> {
+    let size = description.size;
+    let (box_rows, box_cols) = Geometry::boxes_for(size)
+        .ok_or_else(|| format!("f-puzzles has no {}x{} grids", size, size))?;
+    if description
+        .boxes
+        .is_some_and(|boxes| boxes != (box_rows, box_cols))
+    {
+        return Err("f-puzzles only has the usual box shapes".into());
+    }
+    if description.latin {
+        return Err("f-puzzles grids always have regions".into());
+    }
+    if !description.grids.is_empty() {
+        return Err("f-puzzles has no overlapping grids".into());
+    }
+    if !description.greater_than.is_empty() {
+        return Err("f-puzzles has no greater than clues".into());
+    }
+    let digits = |text: &str| -> Result<Vec<usize>, String> {
+        let values = text
+            .chars()
+            .map(|c| match c {
+                '.' => Some(0),
+                _ => c.to_digit(36).map(|value| value as usize),
+            })
+            .collect::<Option<Vec<usize>>>()
+            .filter(|values| values.len() == size * size);
+        values.ok_or_else(|| format!("expected {} cells of 1-{}", size * size, size))
+    };
+
+    let mut regions = vec![None; size * size];
+    for (index, cells) in description.regions.iter().enumerate() {
+        for cell in cells.iter() {
+            regions[*cell] = Some(index);
+        }
+    }
+    let givens = match description.givens.as_str() {
+        "" => vec![0; size * size],
+        givens => digits(givens)?,
+    };
+    let grid = (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|col| {
+                    let cell = row * size + col;
+                    let mut fields = Map::new();
+                    if givens[cell] > 0 {
+                        fields.insert("value".into(), json!(givens[cell]));
+                        fields.insert("given".into(), json!(true));
+                    }
+                    if let Some(region) = regions[cell] {
+                        fields.insert("region".into(), json!(region));
+                    }
+                    Value::Object(fields)
+                })
+                .collect()
+        })
+        .collect::<Vec<Value>>();
+
+    let mut puzzle = Map::new();
+    puzzle.insert("size".into(), json!(size));
+    puzzle.insert("grid".into(), json!(grid));
+    if let Some(solution) = solution {
+        puzzle.insert("solution".into(), json!(digits(solution)?));
+    }
+    let mut push = |key: &str, item: Value| {
+        let items = puzzle.entry(key).or_insert_with(|| json!([]));
+        items.as_array_mut().unwrap().push(item);
+    };
+    for cage in description.cages.iter() {
+        let mut item = json!({ "cells": cell_names(&cage.cells, size) });
+        if let Some(sum) = cage.sum {
+            item["value"] = json!(sum.to_string());
+        }
+        push("killercage", item);
+    }
+    for line in description.lines.iter() {
+        let (key, cells) = match line {
+            Line::Thermo { cells } => ("thermometer", cells),
+            Line::Whispers { cells } => ("whispers", cells),
+            Line::Renban { cells } => ("renban", cells),
+            Line::Palindrome { cells } => ("palindrome", cells),
+            Line::RegionSum { cells } => ("regionsumline", cells),
+            Line::Arrow { cells } => {
+                push(
+                    "arrow",
+                    json!({
+                        "cells": cell_names(&cells[..1], size),
+                        "lines": [cell_names(cells, size)],
+                    }),
+                );
+                continue;
+            }
+        };
+        push(key, json!({ "lines": [cell_names(cells, size)] }));
+    }
+    for dot in description.dots.iter() {
+        let (key, (first, second)) = match *dot {
+            Dot::White { cells } => ("difference", cells),
+            Dot::Black { cells } => ("ratio", cells),
+        };
+        push(key, json!({ "cells": cell_names(&[first, second], size) }));
+    }
+    if description.negative_dots {
+        push("negative", json!("ratio"));
+        push("negative", json!("difference"));
+    }
+    for house in description.extra_houses.iter() {
+        push("extraregion", json!({ "cells": cell_names(house, size) }));
+    }
+    for (key, cells) in [("odd", &description.odd), ("even", &description.even)] {
+        for cell in cells.iter() {
+            push(key, json!({ "cell": cell_name(*cell, size) }));
+        }
+    }
+    if let Some(sandwiches) = &description.sandwiches {
+        for (row, total) in sandwiches.rows.iter().enumerate().take(size) {
+            if let Some(total) = total {
+                let cell = format!("R{}C0", row + 1);
+                push(
+                    "sandwichsum",
+                    json!({ "cell": cell, "value": total.to_string() }),
+                );
+            }
+        }
+        for (col, total) in sandwiches.columns.iter().enumerate().take(size) {
+            if let Some(total) = total {
+                let cell = format!("R0C{}", col + 1);
+                push(
+                    "sandwichsum",
+                    json!({ "cell": cell, "value": total.to_string() }),
+                );
+            }
+        }
+    }
+    if description.non_consecutive {
+        puzzle.insert("nonconsecutive".into(), json!(true));
+    }
+    Ok(puzzle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A classic puzzle with a killer cage, a thermometer, conflict highlighting and some
+    /// decorations, compressed with lz-string's `compressToBase64` as f-puzzles does.
+    const URL: &str = concat!(
+        "https://www.f-puzzles.com/?load=",
+        "N4IgzglgXgpiBcBOANCALhNAbO8QGEsBDMSAYwAIB3TACwoBMYyB7AJyIxYDswRUiAVzS12CcDDRoYbfiDaCcYSe",
+        "IBy7ALZEsFMIIYsA1oIoKlFIgAdLWAJ4A6ObQgBzWlle00+HgDMPZGh88GgKMKgubBAMCADasaAAbtqCuACsERCJ",
+        "MNwIoakAvshJKbgAzJnZuSFhRcB1dSDJWKkIAOyVOXm1xQ29vQC6yAlNpQgAbJ3V+TB99cWjLbgAjFPdhQvNrUhr",
+        "NRslSwgZIC5ZXXuz/fP1QwmNW7goJ2fTPQfbABy7M3O/m2PwSbPKrrS43YbvXBfYHnH5XOaLbZA04gi5/a6I8rfW",
+        "q3SEIAAs2P2CIeCGhKNhb3uAIqMNexKumIQqzpoIKuKZ8A6rLR8MZpPgACYiWD0ZzkS82bjqYdASL0SSAcKeXC8f",
+        "ByZLeeC7ny1YSVW9OSyKfSwZynia2brOcdLWjpbqZZ95daBdy7arzfKBkMQIYIFgcGwyEQXLgRmQYIHgrEQAAlZb",
+        "4MpyBP4fEgX0CkDLAAMIHZqBEMg0LA0khkcVAHm4MBjsbjiHwyxTjcFLaTGYGBfQMAAHmhKyBI9G4vG0vg0hnUFn",
+        "nHJfDxvOIAMQ51druSQWAIHP2NLd6vhqsQGt1+OJ5uoOOC/Btn2oFjCA/4ZcAQRzb7fchoDBE2/sy27MgIGDHBB2",
+        "HLAY3jMoO19AAjEgYGfPAlwAMTQ9C5AfbBj0Q5c13XVB524RdkPw1cv2iX94B3Y5aBgTwB2o3duzYZg0CIbgXFA+",
+        "AIyjCDRzjNp8DaKcQHg5QkJAVD0LQzDHxwySVzIucF0Usi81Qb8qJo1A6IYv9jg4rjcBzdkCiAA=",
+    );
+
+    #[test]
+    fn imports_a_url_with_decorations() {
+        let description = import(URL).unwrap();
+        assert_eq!(
+            description.givens,
+            "530070000600195000098000060800060003400803001700020006060000280000419005000080079"
+        );
+        assert_eq!(
+            description.cages,
+            vec![Cage {
+                cells: vec![2, 3],
+                sum: Some(10)
+            }]
+        );
+        assert_eq!(
+            description.lines,
+            vec![Line::Thermo {
+                cells: vec![72, 73, 74]
+            }]
+        );
+        assert_eq!(
+            description.model().unwrap().solve().as_deref(),
+            Some(
+                "534678912672195348198342567859761423426853791713924856961537284287419635345286179"
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_rules() {
+        let error = import(
+            r#"{"size":4,"grid":[[{},{},{},{}],[{},{},{},{}],[{},{},{},{}],[{},{},{},{}]],"littlekillersum":[{"cell":"R0C1","value":"5"}]}"#,
+        );
+        assert_eq!(
+            error,
+            Err("unsupported f-puzzles constraint littlekillersum".to_string())
+        );
+    }
+
+    #[test]
+    fn round_trips_through_export() {
+        let description = import(URL).unwrap();
+        let exported = export_compressed(&description, None).unwrap();
+        assert_eq!(import(&exported), Ok(description));
+    }
+}
@@ -0,0 +1,374 @@
+use super::bits::Bits;
+
+/// The houses of one grid, or of several overlapping grids laid out on a shared canvas,
+/// for any box shape that fits the wide bitboards.
+#[derive(Clone)]
+pub struct Geometry {
+    pub size: usize,
+    pub width: usize,
+    pub cells: usize,
+    pub(crate) all: Bits,
+    pub(crate) houses: Vec<Bits>,
+    pub(crate) peers: Vec<Bits>,
+    pub(crate) grids: Vec<Vec<usize>>,
+}
+
+impl Geometry {
+    /// A `size` x `size` grid, `size = box_rows * box_cols`, with boxes `box_rows` tall.
+    pub fn new(box_rows: usize, box_cols: usize) -> Result<Self, String> {
+        Geometry::gattai(box_rows, box_cols, &[(0, 0)])
+    }
+
+    /// Overlapping grids whose top left corners sit at the given `(row, col)` offsets.
+    /// Cells covered by several grids are shared, and so are their candidates.
+    pub fn gattai(
+        box_rows: usize,
+        box_cols: usize,
+        offsets: &[(usize, usize)],
+    ) -> Result<Self, String> {
+        if offsets.is_empty() {
+            return Err("no grids".to_string());
+        }
+        let size = box_rows * box_cols;
+        let height = offsets.iter().map(|(top, _)| top + size).max().unwrap_or(0);
+        let width = offsets
+            .iter()
+            .map(|(_, left)| left + size)
+            .max()
+            .unwrap_or(0);
+        let mut geometry = Geometry::canvas(size, width, height)?;
+        for (top, left) in offsets.iter() {
+            geometry.add_grid(*top, *left, Some((box_rows, box_cols)));
+        }
+        Ok(geometry)
+    }
+
+    /// A grid whose boxes are replaced by irregular `regions`, one per digit, which must not
+    /// overlap.
+    pub fn jigsaw(regions: &[Vec<usize>]) -> Result<Self, String> {
+        let size = regions.len();
+        let mut geometry = Geometry::canvas(size, size, size)?;
+        geometry.add_grid(0, 0, None);
+        let mut covered = Bits::empty();
+        for region in regions.iter() {
+            geometry.add_house(region)?;
+            if let Some(cell) = region.iter().find(|cell| covered.has(**cell)) {
+                return Err(format!("cell {} is in two regions", cell));
+            }
+            region.iter().for_each(|cell| covered.set(*cell));
+        }
+        Ok(geometry)
+    }
+
+    /// A Latin square: every row and column holds each of `size` digits once, with no boxes.
+    pub fn latin(size: usize) -> Result<Self, String> {
+        let mut geometry = Geometry::canvas(size, size, size)?;
+        geometry.add_grid(0, 0, None);
+        Ok(geometry)
+    }
+
+    fn canvas(size: usize, width: usize, height: usize) -> Result<Self, String> {
+        if size == 0 || size > 32 {
+            return Err(format!("unsupported size {}", size));
+        }
+        if width * height > Bits::CAPACITY {
+            return Err(format!(
+                "a {}x{} canvas holds more than {} cells",
+                width,
+                height,
+                Bits::CAPACITY
+            ));
+        }
+        Ok(Geometry {
+            size,
+            width,
+            cells: width * height,
+            all: Bits::empty(),
+            houses: vec![],
+            peers: vec![Bits::empty(); width * height],
+            grids: vec![],
+        })
+    }
+
+    pub fn classic() -> Self {
+        Geometry::new(3, 3).unwrap()
+    }
+
+    pub fn twodoku() -> Self {
+        Geometry::gattai(3, 3, &[(0, 0), (6, 6)]).unwrap()
+    }
+
+    pub fn samurai() -> Self {
+        Geometry::gattai(3, 3, &[(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)]).unwrap()
+    }
+
+    pub fn butterfly() -> Self {
+        Geometry::gattai(3, 3, &[(0, 0), (0, 3), (3, 0), (3, 3)]).unwrap()
+    }
+
+    /// The usual box shape for `size` digits: 4x4, 6x6, 9x9, 12x12, 16x16 or 25x25 grids.
+    pub fn boxes_for(size: usize) -> Option<(usize, usize)> {
+        match size {
+            4 => Some((2, 2)),
+            6 => Some((2, 3)),
+            9 => Some((3, 3)),
+            12 => Some((3, 4)),
+            16 => Some((4, 4)),
+            25 => Some((5, 5)),
+            _ => None,
+        }
+    }
+
+    /// The usual grid for a puzzle with `cells` cells.
+    pub fn for_cells(cells: usize) -> Option<Self> {
+        let size = (1..=32).find(|size| size * size == cells)?;
+        let (box_rows, box_cols) = Geometry::boxes_for(size)?;
+        Geometry::new(box_rows, box_cols).ok()
+    }
+
+    fn add_grid(&mut self, top: usize, left: usize, boxes: Option<(usize, usize)>) {
+        let size = self.size;
+        let cells = (0..size * size)
+            .map(|index| (top + index / size) * self.width + left + index % size)
+            .collect::<Vec<usize>>();
+        for cell in cells.iter() {
+            self.all.set(*cell);
+        }
+        for row in 0..size {
+            self.insert_house(&cells[row * size..row * size + size]);
+        }
+        for col in 0..size {
+            self.insert_house(
+                &cells
+                    .iter()
+                    .skip(col)
+                    .step_by(size)
+                    .copied()
+                    .collect::<Vec<usize>>(),
+            );
+        }
+        if let Some((box_rows, box_cols)) = boxes {
+            for index in 0..size {
+                let top = (index / box_rows) * box_rows;
+                let left = (index % box_rows) * box_cols;
+                let house = (0..size)
+                    .map(|position| {
+                        cells[(top + position / box_cols) * size + left + position % box_cols]
+                    })
+                    .collect::<Vec<usize>>();
+                self.insert_house(&house);
+            }
+        }
+        self.grids.push(cells);
+    }
+
+    /// Appends a house which must hold every digit once: `size` distinct cells of the grids.
+    pub fn add_house(&mut self, cells: &[usize]) -> Result<(), String> {
+        if cells.len() != self.size {
+            return Err(format!(
+                "a house needs {} cells, found {}",
+                self.size,
+                cells.len()
+            ));
+        }
+        if let Some(cell) = cells
+            .iter()
+            .find(|cell| **cell >= self.cells || !self.all.has(**cell))
+        {
+            return Err(format!("cell {} is outside the grid", cell));
+        }
+        if let Some(cell) = cells
+            .iter()
+            .enumerate()
+            .find_map(|(index, cell)| cells[..index].contains(cell).then_some(cell))
+        {
+            return Err(format!("cell {} appears twice in a house", cell));
+        }
+        self.insert_house(cells);
+        Ok(())
+    }
+
+    fn insert_house(&mut self, cells: &[usize]) {
+        let mut house = Bits::empty();
+        for cell in cells.iter() {
+            house.set(*cell);
+        }
+        if self.houses.contains(&house) {
+            return;
+        }
+        for cell in cells.iter() {
+            self.peers[*cell] = self.peers[*cell].or(&house);
+            self.peers[*cell].clear(*cell);
+        }
+        self.houses.push(house);
+    }
+
+    /// Reads one character per cell, grid after grid: `1`-`9`, then `A`, `B`, ... for 10 and up,
+    /// `0` or `.` when empty. Givens repeated in overlapping grids must agree.
+    pub fn parse(&self, puzzle: &str) -> Option<Vec<usize>> {
+        let values = puzzle
+            .chars()
+            .map(|c| match c {
+                '.' => Some(0),
+                _ => c
+                    .to_digit(36)
+                    .map(|value| value as usize)
+                    .filter(|value| *value <= self.size),
+            })
+            .collect::<Option<Vec<usize>>>()?;
+        if values.len() != self.grids.iter().map(|grid| grid.len()).sum::<usize>() {
+            return None;
+        }
+        let mut cell_values = vec![0; self.cells];
+        for (cell, value) in self.grids.iter().flatten().zip(values.iter()) {
+            if *value > 0 {
+                if cell_values[*cell] > 0 && cell_values[*cell] != *value {
+                    return None;
+                }
+                cell_values[*cell] = *value;
+            }
+        }
+        Some(cell_values)
+    }
+
+    pub fn format(&self, values: &[usize]) -> String {
+        self.grids
+            .iter()
+            .flatten()
+            .map(|cell| {
+                char::from_digit(values[*cell] as u32, 36)
+                    .unwrap()
+                    .to_ascii_uppercase()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Model;
+    use super::*;
+
+    /// Checks that `solution` keeps the givens of `puzzle`, repeats the same digit wherever
+    /// grids share a cell, and holds every digit once in each house.
+    fn assert_solves(geometry: &Geometry, puzzle: &str, solution: &str) {
+        let givens = geometry.parse(puzzle).unwrap();
+        let mut values = vec![0; geometry.cells];
+        for (cell, digit) in geometry.grids.iter().flatten().zip(solution.chars()) {
+            let value = digit.to_digit(36).unwrap() as usize;
+            assert!(
+                values[*cell] == 0 || values[*cell] == value,
+                "cell {}",
+                cell
+            );
+            values[*cell] = value;
+        }
+        for cell in geometry.all.ones() {
+            assert!(
+                givens[cell] == 0 || givens[cell] == values[cell],
+                "cell {}",
+                cell
+            );
+        }
+        let digits = (1..=geometry.size).collect::<Vec<usize>>();
+        for house in geometry.houses.iter() {
+            let mut held = house
+                .ones()
+                .map(|cell| values[cell])
+                .collect::<Vec<usize>>();
+            held.sort();
+            assert_eq!(held, digits);
+        }
+    }
+
+    /// Solves the empty grid on the wide engine, then the puzzle left by blanking every third
+    /// cell of its solution.
+    fn assert_solvable(geometry: Geometry) {
+        let mut model = Model::new(geometry);
+        let solution = model.solve().unwrap();
+        assert_solves(&model.geometry, &model.puzzle(), &solution);
+        let puzzle = solution
+            .chars()
+            .enumerate()
+            .map(|(index, digit)| if index % 3 == 0 { '0' } else { digit })
+            .collect::<String>();
+        model.givens = model.geometry.parse(&puzzle).unwrap();
+        let solution = model.solve().unwrap();
+        assert_solves(&model.geometry, &puzzle, &solution);
+    }
+
+    #[test]
+    fn solves_every_box_shape() {
+        for (box_rows, box_cols) in [(2, 2), (2, 3), (3, 3), (3, 4), (4, 4), (5, 5)] {
+            assert_solvable(Geometry::new(box_rows, box_cols).unwrap());
+        }
+        assert_solvable(Geometry::latin(5).unwrap());
+    }
+
+    #[test]
+    fn solves_jigsaws() {
+        // The boxes of the band starting at row 3b move right by b columns, wrapping around.
+        let regions = (0..9)
+            .map(|region| {
+                let (band, stack) = (region / 3, region % 3);
+                (0..9)
+                    .map(|index| {
+                        let col = (stack * 3 + band + index % 3) % 9;
+                        (band * 3 + index / 3) * 9 + col
+                    })
+                    .collect::<Vec<usize>>()
+            })
+            .collect::<Vec<Vec<usize>>>();
+        let geometry = Geometry::jigsaw(&regions).unwrap();
+        assert!(geometry
+            .houses
+            .iter()
+            .any(|house| house.has(27) && house.has(35)));
+        assert_solvable(geometry);
+    }
+
+    #[test]
+    fn solves_gattai_layouts() {
+        for (geometry, grids, shared) in [
+            (Geometry::twodoku(), 2, 9),
+            (Geometry::butterfly(), 4, 180),
+            (Geometry::samurai(), 5, 36),
+        ] {
+            assert_eq!(geometry.grids.len(), grids);
+            assert_eq!(geometry.all.count_ones() as usize, grids * 81 - shared);
+            assert_solvable(geometry);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_shapes_and_houses() {
+        assert_eq!(Geometry::gattai(3, 3, &[]).err().unwrap(), "no grids");
+        assert_eq!(Geometry::latin(0).err().unwrap(), "unsupported size 0");
+        assert_eq!(Geometry::new(3, 11).err().unwrap(), "unsupported size 33");
+        assert_eq!(
+            Geometry::new(5, 6).err().unwrap(),
+            format!("a 30x30 canvas holds more than {} cells", Bits::CAPACITY)
+        );
+        let mut geometry = Geometry::new(2, 2).unwrap();
+        assert_eq!(
+            geometry.add_house(&[0, 1, 2]),
+            Err("a house needs 4 cells, found 3".to_string())
+        );
+        assert_eq!(
+            geometry.add_house(&[0, 5, 10, 16]),
+            Err("cell 16 is outside the grid".to_string())
+        );
+        assert_eq!(
+            geometry.add_house(&[0, 5, 10, 5]),
+            Err("cell 5 appears twice in a house".to_string())
+        );
+        assert_eq!(geometry.add_house(&[0, 5, 10, 15]), Ok(()));
+        let regions = vec![vec![0, 1, 4, 5], vec![0, 3, 6, 7], vec![8, 9, 12, 13]];
+        assert!(Geometry::jigsaw(&regions).is_err());
+        let overlapping = [regions, vec![vec![10, 11, 14, 15]]].concat();
+        assert_eq!(
+            Geometry::jigsaw(&overlapping).err().unwrap(),
+            "cell 0 is in two regions"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! The `compressToBase64` / `decompressFromBase64` pair of lz-string, as used in puzzle URLs.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+struct BitReader<'a> {
+    input: &'a [u8],
+    value: u32,
+    position: u32,
+    index: usize,
+}
+
+impl BitReader<'_> {
+    fn next_value(&mut self) -> Option<u32> {
+        let value = match self.input.get(self.index) {
+            Some(c) => BASE64
+                .iter()
+                .position(|key| key == c)
+                .map(|value| value as u32),
+            None => Some(0),
+        };
+        self.index += 1;
+        value
+    }
+
+    fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut result = 0;
+        for bit in 0..bits {
+            if self.value & self.position != 0 {
+                result |= 1 << bit;
+            }
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = 32;
+                self.value = self.next_value()?;
+            }
+        }
+        Some(result)
+    }
+}
+
+pub fn decompress_from_base64(input: &str) -> Option<String> {
+    let input = input.trim().replace(' ', "+");
+    if input.is_empty() {
+        return Some(String::new());
+    }
+    let mut reader = BitReader {
+        input: input.as_bytes(),
+        value: 0,
+        position: 32,
+        index: 0,
+    };
+    reader.value = reader.next_value()?;
+
+    let mut dictionary: Vec<Vec<u16>> = vec![vec![]; 3];
+    let mut enlarge_in = 4;
+    let mut bits = 3;
+    let first = match reader.read(2)? {
+        0 => reader.read(8)? as u16,
+        1 => reader.read(16)? as u16,
+        _ => return Some(String::new()),
+    };
+    dictionary.push(vec![first]);
+    let mut word = vec![first];
+    let mut result = word.clone();
+    loop {
+        if reader.index > input.len() {
+            return None;
+        }
+        let mut code = reader.read(bits)? as usize;
+        match code {
+            0 | 1 => {
+                let c = reader.read(if code == 0 { 8 } else { 16 })? as u16;
+                dictionary.push(vec![c]);
+                code = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return String::from_utf16(&result).ok(),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << bits;
+            bits += 1;
+        }
+        let entry = if code < dictionary.len() {
+            dictionary[code].clone()
+        } else if code == dictionary.len() {
+            let mut entry = word.clone();
+            entry.push(word[0]);
+            entry
+        } else {
+            return None;
+        };
+        result.extend_from_slice(&entry);
+        let mut next = word;
+        next.push(entry[0]);
+        dictionary.push(next);
+        enlarge_in -= 1;
+        word = entry;
+        if enlarge_in == 0 {
+            enlarge_in = 1 << bits;
+            bits += 1;
+        }
+    }
+}
+
+struct BitWriter {
+    output: String,
+    value: u32,
+    position: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, mut value: u32) {
+        for _ in 0..bits {
+            self.value = (self.value << 1) | (value & 1);
+            if self.position == 5 {
+                self.position = 0;
+                self.output.push(BASE64[self.value as usize] as char);
+                self.value = 0;
+            } else {
+                self.position += 1;
+            }
+            value >>= 1;
+        }
+    }
+}
+
+struct Compressor {
+    dictionary: HashMap<Vec<u16>, u32>,
+    to_create: HashSet<Vec<u16>>,
+    writer: BitWriter,
+    enlarge_in: u32,
+    bits: u32,
+}
+
+impl Compressor {
+    fn enlarge(&mut self) {
+        self.enlarge_in -= 1;
+        if self.enlarge_in == 0 {
+            self.enlarge_in = 1 << self.bits;
+            self.bits += 1;
+        }
+    }
+
+    fn emit(&mut self, word: &Vec<u16>) {
+        if self.to_create.remove(word) {
+            if word[0] < 256 {
+                self.writer.write(self.bits, 0);
+                self.writer.write(8, word[0] as u32);
+            } else {
+                self.writer.write(self.bits, 1);
+                self.writer.write(16, word[0] as u32);
+            }
+            self.enlarge();
+        } else {
+            self.writer.write(self.bits, self.dictionary[word]);
+        }
+        self.enlarge();
+    }
+}
+
+pub fn compress_to_base64(input: &str) -> String {
+    let mut compressor = Compressor {
+        dictionary: HashMap::new(),
+        to_create: HashSet::new(),
+        writer: BitWriter {
+            output: String::new(),
+            value: 0,
+            position: 0,
+        },
+        enlarge_in: 2,
+        bits: 2,
+    };
+    let mut size = 3;
+    let mut word: Vec<u16> = vec![];
+    for c in input.encode_utf16() {
+        if let Entry::Vacant(entry) = compressor.dictionary.entry(vec![c]) {
+            entry.insert(size);
+            compressor.to_create.insert(vec![c]);
+            size += 1;
+        }
+        let mut extended = word.clone();
+        extended.push(c);
+        if compressor.dictionary.contains_key(&extended) {
+            word = extended;
+        } else {
+            compressor.emit(&word);
+            compressor.dictionary.insert(extended, size);
+            size += 1;
+            word = vec![c];
+        }
+    }
+    if !word.is_empty() {
+        compressor.emit(&word);
+    }
+    compressor.writer.write(compressor.bits, 2);
+    let written = compressor.writer.output.len();
+    while compressor.writer.output.len() == written {
+        compressor.writer.write(1, 0);
+    }
+
+    let mut output = compressor.writer.output;
+    while output.len() % 4 != 0 {
+        output.push('=');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outputs of the JavaScript library's `compressToBase64`.
+    const KNOWN: [(&str, &str); 7] = [
+        ("", "Q==="),
+        ("a", "IZA="),
+        ("Hello, world", "BIUwNmD2A0AEDukBOYAmQ==="),
+        ("aaaaaaaaaaaaaaaaaaaaaaaa", "IY18aQ=="),
+        ("héllo wörld ✓ 数独", "BYS4NmD2AEDuBvAnMATahkcmoB1NA3TkA==="),
+        (
+            r#"{"size":9,"grid":[[{},{},{},{},{},{},{},{},{}]"#,
+            "N4IgzglgXgpiBcBOANCA5gJwgEwQbT2AF9ljSSzKLryBdIA=",
+        ),
+        ("9[é7独B数B3b3数}ö}A", "JwbQlw7IN04EKA6mMDMAjRcC+A39BBIA"),
+    ];
+
+    #[test]
+    fn matches_the_javascript_library() {
+        for (text, compressed) in KNOWN {
+            assert_eq!(compress_to_base64(text), compressed);
+            assert_eq!(decompress_from_base64(compressed).as_deref(), Some(text));
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let repeated = "{\"value\":1,\"given\":true},".repeat(200);
+        for text in ["x", "😀😀😀x😀", "abababababcabcabcd", &repeated] {
+            assert_eq!(
+                decompress_from_base64(&compress_to_base64(text)).as_deref(),
+                Some(text)
+            );
+        }
+    }
+
+    #[test]
+    fn reads_spaces_as_plus_signs() {
+        assert_eq!(
+            decompress_from_base64("JwbQlw7IN04EKA6mMDMAjRcC A39BBIA").as_deref(),
+            Some("9[é7独B数B3b3数}ö}A")
+        );
+    }
+
+    #[test]
+    fn rejects_characters_outside_base64() {
+        assert_eq!(decompress_from_base64("N4Igz!glg"), None);
+    }
+}
@@ -0,0 +1,43 @@
+use super::constraint::Constraint;
+use super::geometry::Geometry;
+use super::wide::WideBoard;
+
+/// A puzzle ready for the solver: its houses, variant constraints and givens.
+pub struct Model {
+    pub geometry: Geometry,
+    pub constraints: Vec<Box<dyn Constraint>>,
+    pub givens: Vec<usize>,
+}
+
+impl Model {
+    pub fn new(geometry: Geometry) -> Self {
+        let givens = vec![0; geometry.cells];
+        Model {
+            geometry,
+            constraints: vec![],
+            givens,
+        }
+    }
+
+    pub fn add(&mut self, constraint: impl Constraint + 'static) {
+        self.constraints.push(Box::new(constraint));
+    }
+
+    pub fn puzzle(&self) -> String {
+        self.geometry.format(&self.givens)
+    }
+
+    pub fn solve(&self) -> Option<String> {
+        let mut board = WideBoard::new(&self.geometry, &self.constraints, &self.givens);
+        if board.solve() {
+            Some(self.geometry.format(&board.values()))
+        } else {
+            None
+        }
+    }
+
+    /// The number of solutions, counting no further than `limit`.
+    pub fn count(&self, limit: usize) -> usize {
+        WideBoard::new(&self.geometry, &self.constraints, &self.givens).count(limit)
+    }
+}
@@ -0,0 +1,370 @@
+//! The search over digit placements for the classic engine, on an explicit stack so that it
+//! can run a budget of nodes at a time, be paused, resumed or cancelled, and stop at limits.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::band::Placements;
+use super::{Board, SolverConfig, Variant, BIT9};
+
+/// Where a search stands after `run`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    /// Stopped at the node budget; `run` again to carry on.
+    Paused,
+    Solved,
+    /// Every placement has been tried.
+    Unsolvable,
+    /// Stopped at the node or time limit.
+    Timeout,
+    Cancelled,
+}
+
+/// Bounds on the work spent on one puzzle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Limits {
+    pub max_nodes: usize,
+    pub deadline: Option<Instant>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_nodes: usize::MAX,
+            deadline: None,
+        }
+    }
+}
+
+/// Shared with a search so another thread can stop it.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The work a search has done so far.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Stats {
+    pub nodes: usize,
+    /// The most digits being tried at once.
+    pub max_depth: usize,
+    pub elapsed: Duration,
+}
+
+/// How a bounded search ended.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    /// The puzzle could not be read.
+    Invalid(String),
+    Solved(String),
+    Unsolvable,
+    Timeout(Stats),
+    Cancelled(Stats),
+}
+
+/// A digit being tried in each of its placements, and the board to go back to between them.
+struct Frame {
+    number: usize,
+    numbers: usize,
+    number_cells: [u128; 9],
+    placements: Placements,
+}
+
+pub struct Search<'a> {
+    board: Board<'a>,
+    stack: Vec<Frame>,
+    /// The digit to place next and its number of candidates, once the board has propagated.
+    next: Option<(usize, u32)>,
+    status: Status,
+    limits: Limits,
+    cancel: Option<CancelToken>,
+    stats: Stats,
+}
+
+impl<'a> Search<'a> {
+    /// A search for the solution of `puzzle`, 81 cells of `1`-`9` with `0` or `.` for empty.
+    pub fn new(puzzle: &str, variant: &'a Variant, config: SolverConfig) -> Result<Self, String> {
+        let mut cell_values = [0; 81];
+        let mut cells = 0;
+        for (index, c) in puzzle.chars().enumerate() {
+            let value = match c {
+                '.' => 0,
+                _ => c
+                    .to_digit(10)
+                    .ok_or_else(|| format!("bad character {:?} in cell {}", c, index))?,
+            };
+            if let Some(cell_value) = cell_values.get_mut(index) {
+                *cell_value = value as usize;
+            }
+            cells += 1;
+        }
+        if cells != 81 {
+            return Err(format!("expected 81 cells, found {}", cells));
+        }
+        let (board, shortest) = Board::new(cell_values, variant, config);
+        Ok(Search {
+            board,
+            stack: vec![],
+            next: Some(shortest),
+            status: Status::Paused,
+            limits: Limits::default(),
+            cancel: None,
+            stats: Stats::default(),
+        })
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Carries on for at most `max_nodes` more digits placed, within the limits and until the
+    /// cancel token is set.
+    pub fn run(&mut self, max_nodes: usize) -> Status {
+        if self.status != Status::Paused {
+            return self.status;
+        }
+        let started = Instant::now();
+        let budget = self.stats.nodes.saturating_add(max_nodes);
+        self.status = loop {
+            if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                break Status::Cancelled;
+            }
+            if self.out_of_limits() {
+                break Status::Timeout;
+            }
+            if self.stats.nodes >= budget {
+                break Status::Paused;
+            }
+            let status = match self.next.take() {
+                Some(shortest) => self.expand(shortest),
+                None => self.advance(),
+            };
+            self.stats.max_depth = self.stats.max_depth.max(self.stack.len());
+            if let Some(status) = status {
+                break status;
+            }
+        };
+        if self.status != Status::Paused {
+            self.stack.clear();
+            self.next = None;
+        }
+        self.stats.elapsed += started.elapsed();
+        self.status
+    }
+
+    fn out_of_limits(&self) -> bool {
+        self.stats.nodes >= self.limits.max_nodes
+            || self
+                .limits
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Drops the search; `run` reports `Cancelled` from then on.
+    pub fn cancel(&mut self) {
+        if self.status == Status::Paused {
+            self.stack.clear();
+            self.next = None;
+            self.status = Status::Cancelled;
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// The digits placed so far, counting each placement tried.
+    pub fn nodes(&self) -> usize {
+        self.stats.nodes
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// How the search ended, or `None` while it is paused.
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.status {
+            Status::Paused => None,
+            Status::Solved => self.board.solution().map(Outcome::Solved),
+            Status::Unsolvable => Some(Outcome::Unsolvable),
+            Status::Timeout => Some(Outcome::Timeout(self.stats)),
+            Status::Cancelled => Some(Outcome::Cancelled(self.stats)),
+        }
+    }
+
+    pub fn solution(&self) -> Option<String> {
+        match self.status {
+            Status::Solved => self.board.solution(),
+            _ => None,
+        }
+    }
+
+    /// Starts on the digit picked by propagation: one left with nine cells is placed as it
+    /// stands, any other gets a frame to try its placements in turn.
+    fn expand(&mut self, (number, length): (usize, u32)) -> Option<Status> {
+        let board = &mut self.board;
+        if !board.is_sudoku {
+            return None;
+        }
+        board.numbers ^= BIT9[number];
+        let cells = board.number_cells[number];
+        if length == 9 {
+            self.stats.nodes += 1;
+            if !board.is_placement(cells) {
+                board.is_sudoku = false;
+                return None;
+            }
+            return self.placed(number, cells);
+        }
+        self.stack.push(Frame {
+            number,
+            numbers: board.numbers,
+            number_cells: board.number_cells,
+            placements: Placements::new(cells),
+        });
+        None
+    }
+
+    /// Restores the board of the innermost frame and tries its next placement, dropping the
+    /// frame once none are left.
+    fn advance(&mut self) -> Option<Status> {
+        let Some(frame) = self.stack.last_mut() else {
+            return Some(Status::Unsolvable);
+        };
+        let board = &mut self.board;
+        let Some(cells) = frame.placements.find(|cells| board.fits_houses(*cells)) else {
+            self.stack.pop();
+            return None;
+        };
+        board.numbers = frame.numbers;
+        board.number_cells = frame.number_cells;
+        board.number_cells[frame.number] = cells;
+        board.is_sudoku = true;
+        self.stats.nodes += 1;
+        let number = frame.number;
+        self.placed(number, cells)
+    }
+
+    /// Propagates a digit just placed, leaving the next digit to expand.
+    fn placed(&mut self, number: usize, cells: u128) -> Option<Status> {
+        let board = &mut self.board;
+        let constrained = !board.variant.constraints.is_empty();
+        if board.numbers == 0 && !constrained {
+            return Some(Status::Solved);
+        }
+        let shortest = board.remove_single_from_others(number, cells);
+        if !board.is_sudoku {
+            return None;
+        }
+        if board.numbers == 0 {
+            return Some(Status::Solved);
+        }
+        self.next = Some(shortest);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::solve_with_limits;
+    use super::*;
+
+    const PUZZLE: &str =
+        "000000010400000000020000000000050407008000300001090000300400200050100000000806000";
+
+    #[test]
+    fn reports_invalid_puzzles() {
+        for puzzle in [
+            "",
+            "12",
+            &PUZZLE[1..],
+            &format!("{}0", PUZZLE),
+            &PUZZLE.replace('4', "x"),
+        ] {
+            assert!(
+                matches!(
+                    solve_with_limits(puzzle, Limits::default()),
+                    Outcome::Invalid(_)
+                ),
+                "{:?}",
+                puzzle
+            );
+        }
+        let multibyte = format!("é{}", &PUZZLE[2..]);
+        assert!(matches!(
+            solve_with_limits(&multibyte, Limits::default()),
+            Outcome::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn reads_dots_as_empty_cells() {
+        let solved = solve_with_limits(&PUZZLE.replace('0', "."), Limits::default());
+        assert_eq!(solved, solve_with_limits(PUZZLE, Limits::default()));
+        assert!(matches!(solved, Outcome::Solved(_)));
+    }
+
+    #[test]
+    fn stops_at_limits_and_cancel() {
+        let limits = Limits {
+            max_nodes: 1,
+            deadline: None,
+        };
+        assert!(matches!(
+            solve_with_limits(PUZZLE, limits),
+            Outcome::Timeout(Stats { nodes: 1, .. })
+        ));
+
+        let variant = Variant::default();
+        let cancel = CancelToken::new();
+        let mut search = Search::new(PUZZLE, &variant, SolverConfig::default())
+            .unwrap()
+            .with_cancel(cancel.clone());
+        assert_eq!(search.run(1), Status::Paused);
+        cancel.cancel();
+        assert_eq!(search.run(usize::MAX), Status::Cancelled);
+    }
+
+    #[test]
+    fn resumes_one_node_at_a_time() {
+        let solved = include_str!("../../../test-data/forum_hardest_1106.solved");
+        let variant = Variant::default();
+        for line in solved.lines().step_by(53) {
+            let (puzzle, solution) = line.split_once(',').unwrap();
+            let search = || Search::new(puzzle, &variant, SolverConfig::default()).unwrap();
+            let mut whole = search();
+            assert_eq!(whole.run(usize::MAX), Status::Solved);
+            let mut stepped = search();
+            let mut runs = 0;
+            while stepped.run(1) == Status::Paused {
+                runs += 1;
+                assert_eq!(stepped.nodes(), runs);
+            }
+            assert_eq!(stepped.status(), Status::Solved);
+            assert_eq!(stepped.solution().as_deref(), Some(solution));
+            assert_eq!(stepped.solution(), whole.solution());
+            assert_eq!(stepped.nodes(), whole.nodes());
+            assert_eq!(stepped.stats().max_depth, whole.stats().max_depth);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Solving engines behind one trait, picked by name, so they can be checked against each other.
+
+use super::dlx::Dlx;
+use super::geometry::Geometry;
+use super::list::GROUPS;
+use super::variant::{self, Variant};
+use super::{solve_in, Search, SolverConfig};
+
+/// An engine solving the puzzles of one geometry, read and written one character per cell as
+/// `Geometry::parse` and `Geometry::format` do.
+pub trait Solver: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// The solution, `None` when there is none, or an error when the puzzle cannot be read.
+    fn solve(&self, puzzle: &str) -> Result<Option<String>, String>;
+}
+
+/// Checks that `geometry` can read `puzzle`, for engines which would only return `None`.
+fn check_puzzle(geometry: &Geometry, puzzle: &str) -> Result<(), String> {
+    match geometry.parse(puzzle) {
+        Some(_) => Ok(()),
+        None => Err(format!(
+            "expected {} cells of 0-{} or ., agreeing where grids overlap",
+            geometry.grids.iter().map(|grid| grid.len()).sum::<usize>(),
+            geometry.size
+        )),
+    }
+}
+
+/// The band pattern bitboard engine, for 9x9 grids with optional extra houses and variant
+/// constraints.
+pub struct BitboardSolver {
+    variant: Variant,
+    config: SolverConfig,
+}
+
+impl BitboardSolver {
+    pub fn new(variant: Variant, config: SolverConfig) -> Self {
+        BitboardSolver { variant, config }
+    }
+
+    /// The engine for a 9x9 geometry holding every row, column and box, its other houses
+    /// added to the variant.
+    pub fn for_geometry(geometry: &Geometry, config: SolverConfig) -> Option<Self> {
+        if geometry.size != 9 || geometry.cells != 81 {
+            return None;
+        }
+        let houses = geometry
+            .houses
+            .iter()
+            .map(|house| variant::house(&house.ones().collect::<Vec<usize>>()))
+            .collect::<Vec<u128>>();
+        if !GROUPS.iter().all(|group| houses.contains(group)) {
+            return None;
+        }
+        let mut variant = Variant::new();
+        for house in houses.into_iter().filter(|house| !GROUPS.contains(house)) {
+            variant.add_house(house).ok()?;
+        }
+        Some(BitboardSolver::new(variant, config))
+    }
+}
+
+impl Solver for BitboardSolver {
+    fn name(&self) -> &'static str {
+        "bitboard"
+    }
+
+    fn solve(&self, puzzle: &str) -> Result<Option<String>, String> {
+        let mut search = Search::new(puzzle, &self.variant, self.config)?;
+        search.run(usize::MAX);
+        Ok(search.solution())
+    }
+}
+
+/// The cell bitboard engine for any geometry: singles and branching on the cell with the
+/// fewest candidates.
+pub struct WideSolver {
+    geometry: Geometry,
+}
+
+impl WideSolver {
+    pub fn new(geometry: Geometry) -> Self {
+        WideSolver { geometry }
+    }
+}
+
+impl Solver for WideSolver {
+    fn name(&self) -> &'static str {
+        "wide"
+    }
+
+    fn solve(&self, puzzle: &str) -> Result<Option<String>, String> {
+        check_puzzle(&self.geometry, puzzle)?;
+        Ok(solve_in(puzzle, &self.geometry))
+    }
+}
+
+impl Solver for Dlx {
+    fn name(&self) -> &'static str {
+        "dlx"
+    }
+
+    fn solve(&self, puzzle: &str) -> Result<Option<String>, String> {
+        check_puzzle(&self.geometry, puzzle)?;
+        Ok(Dlx::solve(self, puzzle))
+    }
+}
+
+pub const ENGINES: [&str; 3] = ["bitboard", "wide", "dlx"];
+
+/// The engine called `name` for `geometry`. `config` sets how the bitboard engine propagates
+/// and branches; the other engines have no such settings and refuse one.
+pub fn engine(
+    name: &str,
+    geometry: &Geometry,
+    config: Option<SolverConfig>,
+) -> Result<Box<dyn Solver>, String> {
+    match (name, config) {
+        ("bitboard", config) => {
+            match BitboardSolver::for_geometry(geometry, config.unwrap_or_default()) {
+                Some(solver) => Ok(Box::new(solver)),
+                None => Err("bitboard only solves 9x9 grids with rows, columns and boxes".into()),
+            }
+        }
+        ("wide" | "dlx", Some(_)) => Err(format!(
+            "the {} engine has no propagation levels or branching heuristics",
+            name
+        )),
+        ("wide", None) => Ok(Box::new(WideSolver::new(geometry.clone()))),
+        ("dlx", None) => Ok(Box::new(Dlx::new(geometry.clone()))),
+        _ => Err(format!(
+            "Unknown engine {}, expected one of {}",
+            name,
+            ENGINES.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "000000008003000400090020060000079000000061200060502070008000500010000020405000003";
+    const SOLUTION: &str =
+        "621943758783615492594728361142879635357461289869532174238197546916354827475286913";
+
+    #[test]
+    fn engines_read_dots_as_empty_cells() {
+        let dotted = PUZZLE.replace('0', ".");
+        for name in ENGINES {
+            let solver = engine(name, &Geometry::classic(), None).unwrap();
+            let solution = Ok(Some(SOLUTION.to_string()));
+            assert_eq!(solver.solve(PUZZLE), solution, "{}", name);
+            assert_eq!(solver.solve(&dotted), solution, "{}", name);
+            assert!(solver.solve(&dotted[1..]).is_err(), "{}", name);
+            assert!(solver.solve(&PUZZLE.replace('4', "x")).is_err(), "{}", name);
+            let clash = format!("11{}", &PUZZLE[2..]);
+            assert_eq!(solver.solve(&clash), Ok(None), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Generators for the lookup tables in `list.rs`, which instantiates them for 9x9. They take
+//! any box shape whose grid fits the bits of a `u128`, up to 11x11 cells. The build script
+//! includes this file too, so it must not depend on the rest of the crate.
+
+pub const ALL81: u128 = make_all(81);
+
+/// The first `cells` bits.
+pub const fn make_all(cells: usize) -> u128 {
+    if cells == 128 {
+        u128::MAX
+    } else {
+        (1 << cells) - 1
+    }
+}
+
+pub const fn make_digit_bits<const DIGITS: usize>() -> [usize; DIGITS] {
+    let mut bits = [0; DIGITS];
+    let mut index = 0;
+    while index < DIGITS {
+        bits[index] = 1 << index;
+        index += 1;
+    }
+    bits
+}
+
+pub const fn make_cell_bits<const CELLS: usize>() -> [u128; CELLS] {
+    let mut bits = [0; CELLS];
+    let mut index = 0;
+    while index < CELLS {
+        bits[index] = 1 << index;
+        index += 1;
+    }
+    bits
+}
+
+/// The rows, then the columns, then the boxes of a grid with boxes `box_rows` tall and
+/// `box_cols` wide. `HOUSES` is three times the size of the grid.
+pub const fn make_groups<const HOUSES: usize>(box_rows: usize, box_cols: usize) -> [u128; HOUSES] {
+    let size = box_rows * box_cols;
+    assert!(HOUSES == 3 * size && size * size <= 128);
+    let mut groups = [0; HOUSES];
+    let mut cell = 0;
+    while cell < size * size {
+        let (row, col) = (cell / size, cell % size);
+        groups[row] |= 1 << cell;
+        groups[col + size] |= 1 << cell;
+        groups[(row / box_rows) * box_rows + col / box_cols + 2 * size] |= 1 << cell;
+        cell += 1;
+    }
+    groups
+}
+
+/// The cells left to a digit once it is placed in each cell: the cell itself and every cell
+/// sharing none of `groups` with it. Variants pass their extra houses along with the grid's.
+pub const fn make_set_cells<const CELLS: usize>(groups: &[u128]) -> [u128; CELLS] {
+    let mut set_cells = [0; CELLS];
+    let mut cell = 0;
+    while cell < CELLS {
+        let mut peers = 0;
+        let mut index = 0;
+        while index < groups.len() {
+            if groups[index] & 1 << cell != 0 {
+                peers |= groups[index];
+            }
+            index += 1;
+        }
+        set_cells[cell] = (!peers & make_all(CELLS)) | 1 << cell;
+        cell += 1;
+    }
+    set_cells
+}
+
+/// Every placement of one digit on a grid with boxes `box_rows` tall and `box_cols` wide,
+/// walking the columns in order and trying rows top to bottom.
+pub fn make_placements(box_rows: usize, box_cols: usize) -> Vec<u128> {
+    fn walk(
+        (box_rows, box_cols): (usize, usize),
+        col: usize,
+        rows: &mut [usize],
+        placements: &mut Vec<u128>,
+    ) {
+        let size = rows.len();
+        if col == size {
+            placements.push((0..size).fold(0, |cells, col| cells | 1 << (rows[col] * size + col)));
+            return;
+        }
+        for row in 0..size {
+            let taken = (0..col).any(|other| {
+                rows[other] == row
+                    || (rows[other] / box_rows == row / box_rows
+                        && other / box_cols == col / box_cols)
+            });
+            if !taken {
+                rows[col] = row;
+                walk((box_rows, box_cols), col + 1, rows, placements);
+            }
+        }
+    }
+    let size = box_rows * box_cols;
+    assert!(size * size <= 128);
+    let mut placements = vec![];
+    walk((box_rows, box_cols), 0, &mut vec![0; size], &mut placements);
+    placements
+}
+
+/// A middle part and the last parts found with it.
+type Run = (usize, Vec<usize>);
+
+/// The 9x9 placements filed under their first two rows and then their middle part, both in
+/// the order they are first met. The split into two, three and four rows is laid out for the
+/// bands of a 9x9 grid, so unlike the other tables this one has no box shape to choose.
+pub fn make_possibles() -> [[usize; 937]; 54] {
+    let mut lists: Vec<(usize, Vec<Run>)> = vec![];
+    for cells in make_placements(3, 3) {
+        let first = (cells & 0b111111111111111111) as usize;
+        let second = ((cells >> 18) & 0b111111111111111111111111111) as usize;
+        let third = (cells >> 45) as usize;
+        let list = match lists.iter().position(|(known, _)| *known == first) {
+            Some(list) => list,
+            None => {
+                lists.push((first, vec![]));
+                lists.len() - 1
+            }
+        };
+        let runs = &mut lists[list].1;
+        match runs.iter_mut().find(|(known, _)| *known == second) {
+            Some((_, thirds)) => thirds.push(third),
+            None => runs.push((second, vec![third])),
+        }
+    }
+
+    let mut possibles = [[0; 937]; 54];
+    for (possible, (first, runs)) in possibles.iter_mut().zip(lists.iter()) {
+        possible[0] = *first;
+        for (run, (second, thirds)) in runs.iter().enumerate() {
+            possible[1 + run * 13] = *second;
+            possible[2 + run * 13..14 + run * 13].copy_from_slice(thirds);
+        }
+    }
+    possibles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_a_six_by_six_grid() {
+        let groups = make_groups::<18>(2, 3);
+        assert!(groups.iter().all(|group| group.count_ones() == 6));
+        assert_eq!(
+            groups.iter().fold(0, |all, group| all | group),
+            make_all(36)
+        );
+        // The box holding the top right corner spans rows 0-1 and columns 3-5.
+        assert_eq!(groups[13], 0b111000_111000);
+        let set_cells = make_set_cells::<36>(&groups);
+        // Every cell but the 13 in the first row, column and box, and the corner itself.
+        assert_eq!(set_cells[0].count_ones(), 36 - 13 + 1);
+        let placements = make_placements(2, 3);
+        assert!(placements
+            .iter()
+            .all(|cells| groups.iter().all(|group| (cells & group).count_ones() == 1)));
+        // A row in each band for the columns 0-2 (6 * 4 * 2), then the three rows left for
+        // the columns 3-5 (3 * 2 * 1).
+        assert_eq!(placements.len(), 288);
+    }
+}
@@ -0,0 +1,212 @@
+//! Checks of classic grids against the rules: finished solutions against their puzzle, and
+//! partly filled grids for digits repeated in a house.
+
+use std::fmt;
+
+use super::list::GROUPS;
+
+/// A digit held more than once by one house.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    /// The index in `GROUPS`: rows, then columns, then boxes.
+    pub house: usize,
+    pub digit: usize,
+    /// The cells of the house holding the digit, in order.
+    pub cells: Vec<usize>,
+}
+
+/// Why a solution does not solve its puzzle.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Violation {
+    /// The puzzle or the solution is not 81 cells of digits, with `0` or `.` for empty.
+    Malformed,
+    Empty(usize),
+    ChangedGiven {
+        cell: usize,
+        given: usize,
+        value: usize,
+    },
+    Duplicate(Conflict),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Malformed => write!(f, "not 81 cells of digits"),
+            Violation::Empty(cell) => write!(f, "cell {} is empty", cell),
+            Violation::ChangedGiven { cell, given, value } => {
+                write!(
+                    f,
+                    "cell {} holds {} instead of the given {}",
+                    cell, value, given
+                )
+            }
+            Violation::Duplicate(conflict) => write!(
+                f,
+                "{} {} holds {} in cells {:?}",
+                ["row", "column", "box"][conflict.house / 9],
+                conflict.house % 9,
+                conflict.digit,
+                conflict.cells
+            ),
+        }
+    }
+}
+
+/// The cell values of a grid, 0 for empty, or `None` when it is not 81 cells.
+fn values(grid: &str) -> Option<Vec<usize>> {
+    if grid.len() != 81 {
+        return None;
+    }
+    grid.chars()
+        .map(|c| match c {
+            '.' => Some(0),
+            _ => c.to_digit(10).map(|digit| digit as usize),
+        })
+        .collect()
+}
+
+fn conflicts(values: &[usize]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+    for (house, group) in GROUPS.iter().enumerate() {
+        let cells = (0..81)
+            .filter(|cell| group & 1 << cell != 0)
+            .collect::<Vec<usize>>();
+        for digit in 1..=9 {
+            let holding = cells
+                .iter()
+                .copied()
+                .filter(|cell| values[*cell] == digit)
+                .collect::<Vec<usize>>();
+            if holding.len() > 1 {
+                conflicts.push(Conflict {
+                    house,
+                    digit,
+                    cells: holding,
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Checks that `solution` fills every cell, keeps the givens of `puzzle` and repeats no digit
+/// in a row, column or box.
+pub fn validate_solution(puzzle: &str, solution: &str) -> Result<(), Violation> {
+    let (Some(givens), Some(values)) = (self::values(puzzle), self::values(solution)) else {
+        return Err(Violation::Malformed);
+    };
+    for cell in 0..81 {
+        if values[cell] == 0 {
+            return Err(Violation::Empty(cell));
+        }
+        if givens[cell] > 0 && givens[cell] != values[cell] {
+            return Err(Violation::ChangedGiven {
+                cell,
+                given: givens[cell],
+                value: values[cell],
+            });
+        }
+    }
+    match conflicts(&values).into_iter().next() {
+        Some(conflict) => Err(Violation::Duplicate(conflict)),
+        None => Ok(()),
+    }
+}
+
+/// The digits repeated in a house of a partly filled grid, by house then digit, with `0` or
+/// `.` for empty cells. A grid that is not 81 such cells is `Violation::Malformed`: this used
+/// to return a bare `Vec`, empty for such grids, so callers now match on the `Result`.
+pub fn validate_partial(grid: &str) -> Result<Vec<Conflict>, Violation> {
+    let values = values(grid).ok_or(Violation::Malformed)?;
+    Ok(conflicts(&values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUZZLE: &str =
+        "000000008003000400090020060000079000000061200060502070008000500010000020405000003";
+    const SOLUTION: &str =
+        "621943758783615492594728361142879635357461289869532174238197546916354827475286913";
+
+    #[test]
+    fn accepts_solutions() {
+        assert_eq!(validate_solution(PUZZLE, SOLUTION), Ok(()));
+        assert_eq!(
+            validate_solution(&PUZZLE.replace('0', "."), SOLUTION),
+            Ok(())
+        );
+        assert_eq!(validate_partial(PUZZLE), Ok(vec![]));
+        assert_eq!(validate_partial(SOLUTION), Ok(vec![]));
+    }
+
+    #[test]
+    fn reports_the_first_violation() {
+        let empty = format!("{}.{}", &SOLUTION[..40], &SOLUTION[41..]);
+        assert_eq!(validate_solution(PUZZLE, &empty), Err(Violation::Empty(40)));
+        // Cell 8 holds the given 8: swapping it with cell 7 keeps every house valid.
+        let swapped = format!("{}85{}", &SOLUTION[..7], &SOLUTION[9..]);
+        assert_eq!(
+            validate_solution(PUZZLE, &swapped),
+            Err(Violation::ChangedGiven {
+                cell: 8,
+                given: 8,
+                value: 5
+            })
+        );
+        let repeated = format!("2{}", &SOLUTION[1..]);
+        assert_eq!(
+            validate_solution(PUZZLE, &repeated),
+            Err(Violation::Duplicate(Conflict {
+                house: 0,
+                digit: 2,
+                cells: vec![0, 1]
+            }))
+        );
+    }
+
+    #[test]
+    fn reports_every_conflict_of_a_partial_grid() {
+        let grid = format!("{}8{}", &PUZZLE[..6], &PUZZLE[7..]);
+        assert_eq!(
+            validate_partial(&grid),
+            Ok(vec![
+                Conflict {
+                    house: 0,
+                    digit: 8,
+                    cells: vec![6, 8]
+                },
+                Conflict {
+                    house: 20,
+                    digit: 8,
+                    cells: vec![6, 8]
+                },
+            ])
+        );
+        assert_eq!(
+            validate_partial(&grid.replace('0', ".")),
+            validate_partial(&grid)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_grids() {
+        for grid in [
+            "",
+            &PUZZLE[1..],
+            &format!("{}0", PUZZLE),
+            &PUZZLE.replace('4', "x"),
+        ] {
+            assert_eq!(
+                validate_partial(grid),
+                Err(Violation::Malformed),
+                "{:?}",
+                grid
+            );
+            assert_eq!(validate_solution(grid, SOLUTION), Err(Violation::Malformed));
+            assert_eq!(validate_solution(PUZZLE, grid), Err(Violation::Malformed));
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use super::constraint::Constraint;
+use super::list::{ALL81, BIT81, GROUPS, SET_CELLS};
+
+#[derive(Clone, Copy)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+/// Extra rules layered on top of classic sudoku.
+pub struct Variant {
+    pub(crate) groups: Vec<u128>,
+    pub(crate) houses: Vec<u128>,
+    pub(crate) set_cells: [u128; 81],
+    pub(crate) number_masks: [u128; 9],
+    pub(crate) constraints: Vec<Box<dyn Constraint>>,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant {
+            groups: GROUPS.to_vec(),
+            houses: vec![],
+            set_cells: SET_CELLS,
+            number_masks: [ALL81; 9],
+            constraints: vec![],
+        }
+    }
+}
+
+impl Variant {
+    pub fn new() -> Self {
+        Variant::default()
+    }
+
+    pub fn with(mut self, constraint: impl Constraint + 'static) -> Self {
+        self.add(constraint);
+        self
+    }
+
+    pub fn add(&mut self, constraint: impl Constraint + 'static) {
+        self.constraints.push(Box::new(constraint));
+    }
+
+    pub fn with_house(mut self, house: u128) -> Result<Self, String> {
+        self.add_house(house)?;
+        Ok(self)
+    }
+
+    /// Appends a 9-cell house which must hold every digit once, on top of `GROUPS`.
+    pub fn add_house(&mut self, house: u128) -> Result<(), String> {
+        if house & !ALL81 != 0 {
+            let cell = (house & !ALL81).trailing_zeros();
+            return Err(format!("cell {} is outside the grid", cell));
+        }
+        if house.count_ones() != 9 {
+            return Err(format!(
+                "a house needs 9 cells, found {}",
+                house.count_ones()
+            ));
+        }
+        for (cell_index, set_cells) in self.set_cells.iter_mut().enumerate() {
+            if house & BIT81[cell_index] != 0 {
+                *set_cells &= !house | BIT81[cell_index];
+            }
+        }
+        self.groups.push(house);
+        self.houses.push(house);
+        Ok(())
+    }
+
+    pub fn with_parity(mut self, cell: usize, parity: Parity) -> Self {
+        self.add_parity(cell, parity);
+        self
+    }
+
+    /// Shaded cells: the digits of the other parity are masked out of the cell up front.
+    pub fn add_parity(&mut self, cell: usize, parity: Parity) {
+        let excluded = match parity {
+            Parity::Even => 0,
+            Parity::Odd => 1,
+        };
+        for number in (excluded..9).step_by(2) {
+            self.number_masks[number] &= !BIT81[cell];
+        }
+    }
+}
+
+pub fn house(cells: &[usize]) -> u128 {
+    cells.iter().fold(0, |house, cell| house | BIT81[*cell])
+}
+
+/// The cells of a house, in order.
+pub fn cells(house: u128) -> Vec<usize> {
+    (0..81).filter(|cell| house & BIT81[*cell] != 0).collect()
+}
+
+/// The four shaded 3x3 windows of hyper-sudoku.
+pub fn windoku() -> Vec<u128> {
+    [10, 14, 46, 50]
+        .iter()
+        .map(|corner| house(&[0, 1, 2, 9, 10, 11, 18, 19, 20].map(|cell| corner + cell)))
+        .collect()
+}
+
+/// Cells sharing the same position inside each box.
+pub fn disjoint_groups() -> Vec<u128> {
+    (0..9)
+        .map(|position| {
+            let offset = (position / 3) * 9 + position % 3;
+            house(&[0, 3, 6, 27, 30, 33, 54, 57, 60].map(|corner| corner + offset))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{count_in, solve_with, validate_solution, Geometry, Model};
+    use super::*;
+
+    /// Solves `puzzle` with the extra `houses` on the bitboard and wide engines, checking each
+    /// solution against the rules and the houses.
+    fn solve_both(puzzle: &str, houses: &[u128]) -> [Option<String>; 2] {
+        let mut variant = Variant::new();
+        let mut geometry = Geometry::classic();
+        for house in houses.iter() {
+            variant.add_house(*house).unwrap();
+            geometry.add_house(&cells(*house)).unwrap();
+        }
+        let mut model = Model::new(geometry);
+        model.givens = model.geometry.parse(puzzle).unwrap();
+        let solutions = [solve_with(puzzle, &variant), model.solve()];
+        for solution in solutions.iter().flatten() {
+            assert_eq!(validate_solution(puzzle, solution), Ok(()));
+            for house in houses.iter() {
+                let mut digits = cells(*house)
+                    .iter()
+                    .map(|cell| solution.as_bytes()[*cell])
+                    .collect::<Vec<u8>>();
+                digits.sort();
+                assert_eq!(digits, b"123456789", "{}", solution);
+            }
+        }
+        solutions
+    }
+
+    fn assert_solves(puzzle: &str, solution: &str, houses: &[u128]) {
+        assert_eq!(count_in(puzzle, &Geometry::classic(), 2), Some(2));
+        let solution = Some(solution.to_string());
+        assert_eq!(solve_both(puzzle, houses), [solution.clone(), solution]);
+    }
+
+    #[test]
+    fn solves_windoku() {
+        assert_solves(
+            "000000080000709000080100000030000001000000507000060200002070000908000000600000010",
+            "123456789456789123789123456534297861261834597897561234312678945948315672675942318",
+            &windoku(),
+        );
+        let [bitboard, wide] = solve_both(&"0".repeat(81), &windoku());
+        assert!(bitboard.is_some() && wide.is_some());
+        // Cells 10 and 30 share a window but no row, column or box.
+        let clash = format!("{}1{}1{}", "0".repeat(10), "0".repeat(19), "0".repeat(50));
+        assert_eq!(solve_both(&clash, &windoku()), [None, None]);
+    }
+
+    #[test]
+    fn solves_disjoint_groups() {
+        assert_solves(
+            "000006080050709000000100406030000005000010304000000200002060000508000000000005010",
+            "123456789456789123789123456231674895875912364694538271912367548548291637367845912",
+            &disjoint_groups(),
+        );
+        // Cells 0 and 30 are the first cells of their boxes.
+        let clash = format!("1{}1{}", "0".repeat(29), "0".repeat(50));
+        assert_eq!(solve_both(&clash, &disjoint_groups()), [None, None]);
+    }
+
+    #[test]
+    fn rejects_malformed_houses() {
+        let mut variant = Variant::new();
+        assert_eq!(
+            variant.add_house(house(&[0, 1, 2])),
+            Err("a house needs 9 cells, found 3".to_string())
+        );
+        assert_eq!(
+            variant.add_house(house(&[0, 1, 2, 3, 4, 5, 6, 7]) | 1 << 81),
+            Err("cell 81 is outside the grid".to_string())
+        );
+        assert!(variant.houses.is_empty());
+        assert_eq!(variant.add_house(windoku()[0]), Ok(()));
+    }
+}
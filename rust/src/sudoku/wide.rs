@@ -0,0 +1,199 @@
+use super::bits::Bits;
+use super::constraint::Constraint;
+use super::geometry::Geometry;
+
+/// The digit bitboard engine for any `Geometry`: singles propagation and branching on the cell
+/// with the fewest candidates, where the 9x9 band pattern tables do not apply.
+#[derive(Clone)]
+pub(crate) struct WideBoard<'a> {
+    geometry: &'a Geometry,
+    constraints: &'a [Box<dyn Constraint>],
+    number_cells: Vec<Bits>,
+    settled: Bits,
+}
+
+impl<'a> WideBoard<'a> {
+    pub(crate) fn new(
+        geometry: &'a Geometry,
+        constraints: &'a [Box<dyn Constraint>],
+        values: &[usize],
+    ) -> Self {
+        let mut board = WideBoard {
+            geometry,
+            constraints,
+            number_cells: vec![geometry.all; geometry.size],
+            settled: Bits::empty(),
+        };
+        for (cell, value) in values.iter().enumerate() {
+            if *value > 0 {
+                board.place(value - 1, cell);
+            }
+        }
+        board
+    }
+
+    fn place(&mut self, number: usize, cell: usize) -> bool {
+        self.settled.set(cell);
+        let mut changed = false;
+        for (other, cells) in self.number_cells.iter_mut().enumerate() {
+            let kept = if other == number {
+                cells.and_not(&self.geometry.peers[cell])
+            } else {
+                let mut kept = *cells;
+                kept.clear(cell);
+                kept
+            };
+            if kept != *cells {
+                *cells = kept;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn propagate(&mut self) -> bool {
+        loop {
+            let mut changed = false;
+
+            let (mut once, mut twice) = (Bits::empty(), Bits::empty());
+            for cells in self.number_cells.iter() {
+                twice = twice.or(&once.and(cells));
+                once = once.or(cells);
+            }
+            if once != self.geometry.all {
+                return false;
+            }
+            let singles = once.and_not(&twice).and_not(&self.settled);
+            for number in 0..self.geometry.size {
+                for cell in self.number_cells[number].and(&singles).ones() {
+                    changed |= self.place(number, cell);
+                }
+            }
+
+            for number in 0..self.geometry.size {
+                for house in self.geometry.houses.iter() {
+                    let cells = self.number_cells[number].and(house);
+                    match cells.count_ones() {
+                        0 => return false,
+                        1 => {
+                            let cell = cells.first().unwrap();
+                            if !self.settled.has(cell) {
+                                changed |= self.place(number, cell);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if !changed && !self.constraints.is_empty() {
+                match self.propagate_constraints() {
+                    Some(constrained) => changed = constrained,
+                    None => return false,
+                }
+            }
+            if !changed {
+                return true;
+            }
+        }
+    }
+
+    fn propagate_constraints(&mut self) -> Option<bool> {
+        let original = self.candidates();
+        let mut candidates = original.clone();
+        loop {
+            let before = candidates.clone();
+            for constraint in self.constraints.iter() {
+                if !constraint.propagate(&mut candidates) {
+                    return None;
+                }
+            }
+            if before == candidates {
+                break;
+            }
+        }
+        if candidates == original {
+            return Some(false);
+        }
+        for (cell, (after, before)) in candidates.iter().zip(original.iter()).enumerate() {
+            let mut removed = before & !after;
+            while removed != 0 {
+                self.number_cells[removed.trailing_zeros() as usize].clear(cell);
+                removed &= removed - 1;
+            }
+        }
+        Some(true)
+    }
+
+    fn candidates(&self) -> Vec<u32> {
+        let mut candidates = vec![0; self.geometry.cells];
+        for (number, cells) in self.number_cells.iter().enumerate() {
+            for cell in cells.ones() {
+                candidates[cell] |= 1 << number;
+            }
+        }
+        candidates
+    }
+
+    /// The unsettled cell with the fewest candidates, or `None` once every cell has one.
+    fn branch(&self) -> Option<(usize, u32)> {
+        self.candidates()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, digits)| digits.count_ones() > 1)
+            .min_by_key(|(_, digits)| digits.count_ones())
+    }
+
+    pub(crate) fn solve(&mut self) -> bool {
+        match self.search(1) {
+            (_, Some(solved)) => {
+                *self = solved;
+                true
+            }
+            (_, None) => false,
+        }
+    }
+
+    /// The number of solutions, counting no further than `limit`.
+    pub(crate) fn count(&mut self, limit: usize) -> usize {
+        self.search(limit).0
+    }
+
+    /// Depth first over an explicit stack of branches, so deep grids cannot overflow the
+    /// thread stack. Returns the solutions found up to `limit` and the first of them.
+    fn search(&self, limit: usize) -> (usize, Option<WideBoard<'a>>) {
+        let mut found = 0;
+        let mut first = None;
+        let mut stack: Vec<(WideBoard<'a>, usize, u32)> = vec![];
+        let mut board = self.clone();
+        while found < limit {
+            if board.propagate() {
+                match board.branch() {
+                    Some((cell, digits)) => stack.push((board, cell, digits)),
+                    None => {
+                        found += 1;
+                        first.get_or_insert(board);
+                    }
+                }
+            }
+            while stack.last().is_some_and(|branch| branch.2 == 0) {
+                stack.pop();
+            }
+            let Some((next, cell, digits)) = stack.last_mut() else {
+                break;
+            };
+            let number = digits.trailing_zeros() as usize;
+            *digits &= *digits - 1;
+            board = next.clone();
+            board.place(number, *cell);
+        }
+        (found, first)
+    }
+
+    pub(crate) fn values(&self) -> Vec<usize> {
+        self.candidates()
+            .iter()
+            .map(|digits| digits.trailing_zeros() as usize + 1)
+            .collect()
+    }
+}